@@ -0,0 +1,108 @@
+use crate::error::CliError;
+use haskell_taboo::ALLOW_MARKER;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Fails unless each of `paths` is in a git work tree without uncommitted changes to
+/// it, so that `action`, which rewrites them, can be reviewed and undone with git.
+///
+/// Each work tree is asked only about its own files, so changes to other files, or
+/// running from outside the work trees the files are in, don't get in the way.
+pub fn require_clean_tree<'a>(
+    action: &'static str,
+    paths: impl IntoIterator<Item = &'a Path>,
+) -> Result<(), CliError> {
+    let mut roots: HashMap<PathBuf, Option<PathBuf>> = HashMap::new();
+    let mut files_by_root: BTreeMap<PathBuf, Vec<PathBuf>> = BTreeMap::new();
+    for path in paths {
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        let root = match roots.get(dir) {
+            Some(root) => root.clone(),
+            None => {
+                let root = git_root_of(dir)?;
+                roots.insert(dir.to_path_buf(), root.clone());
+                root
+            }
+        };
+        let (Some(root), Ok(file)) = (root, path.canonicalize()) else {
+            return Err(CliError::DirtyTree { action });
+        };
+        files_by_root.entry(root).or_default().push(file);
+    }
+
+    for (root, files) in files_by_root {
+        let status = Command::new("git")
+            .arg("-C")
+            .arg(&root)
+            .args(["status", "--porcelain", "--untracked-files=no", "--"])
+            .args(&files)
+            .output()
+            .map_err(|source| CliError::Git { source })?;
+        if !status.status.success() || !status.stdout.is_empty() {
+            return Err(CliError::DirtyTree { action });
+        }
+    }
+    Ok(())
+}
+
+/// The canonical top-level directory of the git work tree containing `dir`, or `None`
+/// if it isn't in one.
+fn git_root_of(dir: &Path) -> Result<Option<PathBuf>, CliError> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["rev-parse", "--show-toplevel"])
+        .output()
+        .map_err(|source| CliError::Git { source })?;
+    if !output.status.success() {
+        return Ok(None);
+    }
+    let root = String::from_utf8_lossy(&output.stdout);
+    Ok(PathBuf::from(root.trim_end()).canonicalize().ok())
+}
+
+/// Appends [`ALLOW_MARKER`] to the lines of `path` ending at each of `line_ends`, which
+/// are byte offsets, returning how many lines were annotated.
+///
+/// Lines that already carry the marker are left alone, so annotating twice changes
+/// nothing.
+pub fn annotate_file(path: &Path, line_ends: &[usize]) -> Result<usize, CliError> {
+    let error = |source| CliError::Annotate {
+        path: path.to_path_buf(),
+        source,
+    };
+    let mut source = std::fs::read(path).map_err(error)?;
+
+    let mut line_ends = line_ends.to_vec();
+    line_ends.sort_unstable();
+    line_ends.dedup();
+
+    let mut annotated = 0;
+    // from the end, so that earlier offsets stay valid
+    for &line_end in line_ends.iter().rev() {
+        let line_start = source[..line_end]
+            .iter()
+            .rposition(|b| *b == b'\n')
+            .map_or(0, |newline| newline + 1);
+        let line = &source[line_start..line_end];
+        if line
+            .windows(ALLOW_MARKER.len())
+            .any(|window| window == ALLOW_MARKER.as_bytes())
+        {
+            continue;
+        }
+
+        let marker = format!(" {ALLOW_MARKER}");
+        source.splice(line_end..line_end, marker.bytes());
+        annotated += 1;
+    }
+
+    if annotated > 0 {
+        std::fs::write(path, &source).map_err(error)?;
+    }
+    Ok(annotated)
+}
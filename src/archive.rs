@@ -0,0 +1,69 @@
+use crate::error::CliError;
+use crate::paths::is_haskell_source;
+use flate2::read::GzDecoder;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// Reads every Haskell source file in a `.tar`, `.tar.gz`/`.tgz` or `.zip` archive into
+/// memory, paired with its name inside the archive.
+pub fn haskell_entries(archive: &Path) -> Result<Vec<(PathBuf, Vec<u8>)>, CliError> {
+    let name = archive.to_string_lossy();
+    let read_error = |source| CliError::Archive {
+        path: archive.to_path_buf(),
+        source,
+    };
+    let file = File::open(archive).map_err(|e| read_error(e.into()))?;
+
+    let entries = if name.ends_with(".zip") {
+        zip_entries(file)
+    } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+        tar_entries(GzDecoder::new(file))
+    } else if name.ends_with(".tar") {
+        tar_entries(file)
+    } else {
+        return Err(CliError::UnsupportedArchive {
+            path: archive.to_path_buf(),
+        });
+    };
+
+    entries.map_err(read_error)
+}
+
+type ArchiveError = Box<dyn std::error::Error + Send + Sync>;
+
+fn tar_entries(reader: impl Read) -> Result<Vec<(PathBuf, Vec<u8>)>, ArchiveError> {
+    let mut sources = Vec::new();
+    for entry in tar::Archive::new(reader).entries()? {
+        let mut entry = entry?;
+        let name = entry.path()?.into_owned();
+        if !entry.header().entry_type().is_file() || !is_haskell_source(&name) {
+            continue;
+        }
+
+        let mut bytes = Vec::new();
+        entry.read_to_end(&mut bytes)?;
+        sources.push((name, bytes));
+    }
+    Ok(sources)
+}
+
+fn zip_entries(file: File) -> Result<Vec<(PathBuf, Vec<u8>)>, ArchiveError> {
+    let mut archive = zip::ZipArchive::new(file)?;
+    let mut sources = Vec::new();
+    for index in 0..archive.len() {
+        let mut entry = archive.by_index(index)?;
+        // names that would escape the archive, like `../A.hs`, are skipped
+        let Some(name) = entry.enclosed_name() else {
+            continue;
+        };
+        if !entry.is_file() || !is_haskell_source(&name) {
+            continue;
+        }
+
+        let mut bytes = Vec::new();
+        entry.read_to_end(&mut bytes)?;
+        sources.push((name, bytes));
+    }
+    Ok(sources)
+}
@@ -0,0 +1,115 @@
+use crate::error::CliError;
+use crate::json::TOOL;
+use haskell_taboo::{Found, TabooError};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// What was found in each file scanned with `--cache`, so that files that haven't
+/// changed since aren't parsed again.
+///
+/// An entry is only used while its key does, which hashes everything that decides what
+/// is found: the file's contents, the taboo files its entries came from and the options
+/// for matching them. Entries are named by path, which `in` annotations depend on too.
+pub struct Cache {
+    path: PathBuf,
+    entries: BTreeMap<String, CacheEntry>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CacheFile {
+    /// The version of the tool that wrote the cache, which only reads its own.
+    version: String,
+    entries: BTreeMap<String, CacheEntry>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct CacheEntry {
+    key: String,
+    found: Found,
+}
+
+impl Cache {
+    /// Reads the cache at `path`, starting an empty one if there isn't a cache there
+    /// that this version of the tool can use.
+    pub fn load(path: &Path) -> Cache {
+        let entries = fs::read(path)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice::<CacheFile>(&bytes).ok())
+            .filter(|file| file.version == TOOL.version)
+            .map(|file| file.entries)
+            .unwrap_or_default();
+
+        Cache {
+            path: path.to_path_buf(),
+            entries,
+        }
+    }
+
+    /// Returns what was found in the file at `path` when it last had `key`, if anything.
+    pub fn get(&self, path: &Path, key: &str) -> Option<&Found> {
+        let entry = self.entries.get(path.to_str()?)?;
+        (entry.key == key).then_some(&entry.found)
+    }
+
+    /// Records what was found in the file at `path`, replacing what was found before.
+    ///
+    /// Paths that aren't valid UTF-8 aren't cached, since they can't be written as JSON.
+    pub fn insert(&mut self, path: &Path, key: String, found: Found) {
+        if let Some(path) = path.to_str() {
+            self.entries
+                .insert(path.to_string(), CacheEntry { key, found });
+        }
+    }
+
+    /// Writes the cache back to where it was loaded from, along with the entries of
+    /// any files that weren't scanned this time.
+    pub fn save(self) -> Result<(), CliError> {
+        let file = CacheFile {
+            version: TOOL.version.to_string(),
+            entries: self.entries,
+        };
+        let cache_error = |source| CliError::CacheFile {
+            path: self.path.clone(),
+            source,
+        };
+
+        // written next to the cache and renamed over it, so that an interrupted run
+        // can't leave half a cache behind
+        let partial = self.path.with_extension("partial");
+        let json = serde_json::to_vec(&file).map_err(|e| cache_error(e.into()))?;
+        fs::write(&partial, json).map_err(cache_error)?;
+        fs::rename(&partial, &self.path).map_err(cache_error)
+    }
+}
+
+/// Hashes `settings` along with the taboo files at `taboo_files`, into the part of a
+/// key that every source checked against the entries of those files shares.
+pub fn set_key(settings: &str, taboo_files: &[PathBuf]) -> Result<String, CliError> {
+    let mut hasher = Sha256::new();
+    hash_part(&mut hasher, settings.as_bytes());
+    for path in taboo_files {
+        let bytes = fs::read(path).map_err(|source| TabooError::TabooFile {
+            path: path.clone(),
+            source,
+        })?;
+        hash_part(&mut hasher, &bytes);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// The key of `source` when checked against the entries hashed into `set_key`.
+pub fn key(set_key: &str, source: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hash_part(&mut hasher, set_key.as_bytes());
+    hash_part(&mut hasher, source);
+    format!("{:x}", hasher.finalize())
+}
+
+fn hash_part(hasher: &mut Sha256, part: &[u8]) {
+    // the length keeps `ab` + `c` and `a` + `bc` apart
+    hasher.update((part.len() as u64).to_le_bytes());
+    hasher.update(part);
+}
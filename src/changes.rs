@@ -0,0 +1,93 @@
+use crate::error::CliError;
+use crate::paths::git_root;
+use std::collections::HashMap;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// The lines that `git diff` shows as added or changed in each file, so that only the
+/// violations on them are reported.
+pub struct ChangedLines {
+    /// The 1-based line numbers of each file's hunks, by canonical path.
+    files: HashMap<PathBuf, Vec<Range<usize>>>,
+}
+
+impl ChangedLines {
+    /// Diffs the work tree, or the index if `staged`, against where the current branch
+    /// forked from `since`, or the index against `HEAD` without it.
+    pub fn load(since: Option<&str>, staged: bool) -> Result<Self, CliError> {
+        // outside a work tree, git diff would compare paths instead
+        let root = git_root().ok_or_else(|| CliError::GitDiffFailed {
+            message: "not in a git work tree".to_string(),
+        })?;
+        let mut command = Command::new("git");
+        command.args([
+            "diff",
+            "--no-ext-diff",
+            "--no-color",
+            "--no-prefix",
+            "--unified=0",
+        ]);
+        if staged {
+            command.arg("--cached");
+        }
+        if let Some(since) = since {
+            command.args(["--merge-base", since]);
+        }
+        let output = command
+            .arg("--")
+            .output()
+            .map_err(|source| CliError::GitDiff { source })?;
+        if !output.status.success() {
+            return Err(CliError::GitDiffFailed {
+                message: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            });
+        }
+
+        let mut files: HashMap<PathBuf, Vec<Range<usize>>> = HashMap::new();
+        let mut file = None;
+        let mut in_header = false;
+        for line in output.stdout.split(|b| *b == b'\n') {
+            let line = String::from_utf8_lossy(line);
+            if line.starts_with("diff --git ") {
+                in_header = true;
+                file = None;
+            } else if in_header && let Some(path) = line.strip_prefix("+++ ") {
+                // paths with spaces end with a tab, and deleted files are `/dev/null`
+                file = root.join(path.trim_end_matches('\t')).canonicalize().ok();
+            } else if line.starts_with("@@ ") {
+                // the lines of a hunk's content can look like headers, so only the
+                // lines before its first hunk are read as such
+                in_header = false;
+                if let Some(file) = &file
+                    && let Some(lines) = hunk_lines(&line)
+                {
+                    files.entry(file.clone()).or_default().push(lines);
+                }
+            }
+        }
+        Ok(ChangedLines { files })
+    }
+
+    /// The lines changed in the file at `path`, which are none for a file git doesn't
+    /// track or that isn't on disk.
+    pub fn in_file(&self, path: &Path) -> &[Range<usize>] {
+        path.canonicalize()
+            .ok()
+            .and_then(|path| self.files.get(&path))
+            .map_or(&[], Vec::as_slice)
+    }
+}
+
+/// The lines of the new file covered by the hunk with the header `line`, like
+/// `@@ -3,2 +3,4 @@`, or `None` if it only removes lines.
+fn hunk_lines(line: &str) -> Option<Range<usize>> {
+    let new = line
+        .split_whitespace()
+        .find_map(|part| part.strip_prefix('+'))?;
+    let (start, count) = match new.split_once(',') {
+        Some((start, count)) => (start.parse().ok()?, count.parse().ok()?),
+        None => (new.parse().ok()?, 1),
+    };
+    (count > 0).then_some(start..start + count)
+}
@@ -0,0 +1,213 @@
+use crate::TabooError;
+use crate::edit::{Edit, edit_for};
+use crate::literate::haskell_source;
+use crate::scanner::{
+    Found, Kind, NearMiss, Occurrence, ScanOptions, Scanner, line_around, new_parser, scan_queries,
+};
+use crate::suppress::{Suppression, suppression};
+use crate::taboo::{EntryKind, MatchRule, Severity, TabooMatch, TabooSet};
+use std::cell::RefCell;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use tree_sitter::Parser;
+
+thread_local! {
+    /// The parser of each thread finding banned words, set up the first time it's needed.
+    static PARSER: RefCell<Option<Parser>> = const { RefCell::new(None) };
+}
+
+/// What a [`Checker`] checks for, as set by the options of the same names on the
+/// command line.
+#[derive(Debug, Clone)]
+pub struct CheckOptions {
+    pub scan: ScanOptions,
+    /// The kinds of identifiers that entries outside of `[query]` and `[kinds]`
+    /// sections are checked against.
+    pub kinds: Vec<Kind>,
+    /// Whether entries outside of sections are module names, checked against imports.
+    pub ban_modules: bool,
+}
+
+impl Default for CheckOptions {
+    fn default() -> Self {
+        CheckOptions {
+            scan: ScanOptions::default(),
+            kinds: Kind::DEFAULT.to_vec(),
+            ban_modules: false,
+        }
+    }
+}
+
+/// Checks Haskell sources against a banned words list, for embedding in editors,
+/// build scripts and other tools.
+///
+/// A checker compiles its queries once, so checking many sources with the same one is
+/// much cheaper than setting up a checker for each. It can check sources on several
+/// threads at once.
+pub struct Checker<'a> {
+    taboo_set: &'a TabooSet,
+    scanner: Scanner<'a>,
+}
+
+/// A banned word found by [`Checker::check`], owning everything it describes.
+#[derive(Debug, Clone)]
+pub struct Finding {
+    pub path: PathBuf,
+    /// The 0-based byte span of `identifier` in the source.
+    pub span: Range<usize>,
+    /// The 1-based line the span starts on.
+    pub line: usize,
+    /// The 0-based byte offset of the span within its line.
+    pub column: usize,
+    /// The 1-based line the span ends on.
+    pub end_line: usize,
+    /// The 0-based byte offset of the end of the span within its line.
+    pub end_column: usize,
+    /// The part of `span` that matched the entry, which is all of it unless a literal
+    /// entry matched within the identifier with `MatchOptions::substring`.
+    pub matched_span: Range<usize>,
+    /// The source text that matched, which is qualified with `ScanOptions::qualified`.
+    pub identifier: String,
+    /// The 0-based byte span of the line holding `span`, without its line ending.
+    pub line_span: Range<usize>,
+    /// The whole of the line holding the span, without its line ending.
+    pub line_text: String,
+    /// The banned entry it matched.
+    pub word: String,
+    /// The entry's alias group, or the entry itself outside of one.
+    pub canonical: String,
+    pub severity: Severity,
+    pub kind: EntryKind,
+    /// The capture naming where the word was found, like `pragma` or `TH`, if any.
+    pub label: Option<String>,
+    /// The number of violations of the entry needed before it is reported, which is
+    /// left to the caller to count, since the CLI counts them across a whole run.
+    pub min_occurrences: Option<usize>,
+    /// Why `identifier` matched, as reported by `--explain`.
+    pub explanation: String,
+    /// The edit replacing the word, if its entry has a replacement.
+    pub fix: Option<Edit>,
+}
+
+/// What [`Checker::check_found`] sorted out of what was found in a source.
+#[derive(Debug, Clone, Default)]
+pub struct Checked {
+    /// The violations to report, in the order they appear.
+    pub findings: Vec<Finding>,
+    /// The violations that an allow marker, ignore comment or `[allow]` entry permits,
+    /// along with which of them does.
+    pub suppressed: Vec<(Finding, Suppression)>,
+    /// The banned words that an exception permits, along with the exception.
+    pub near_misses: Vec<(Finding, String)>,
+}
+
+impl<'a> Checker<'a> {
+    /// Sets up a checker of the entries of `taboo_set`, which fails if one of its
+    /// `[query]` sections has a query that doesn't compile.
+    pub fn new(taboo_set: &'a TabooSet, options: &CheckOptions) -> Result<Self, TabooError> {
+        let language = tree_sitter_haskell::LANGUAGE.into();
+        let queries = scan_queries(&language, taboo_set, &options.kinds, options.ban_modules)?;
+        Ok(Checker {
+            taboo_set,
+            scanner: Scanner::new(queries, options.scan.clone()),
+        })
+    }
+
+    /// The banned words list being checked against.
+    pub fn taboo_set(&self) -> &'a TabooSet {
+        self.taboo_set
+    }
+
+    /// Checks `source`, the contents of the file at `path`, returning its violations in
+    /// the order they appear.
+    ///
+    /// The path decides which `in` annotations and `[allow]` entries apply, and whether
+    /// the source is literate Haskell. Violations that an exception, allow marker,
+    /// ignore comment or `[allow]` entry permits are left out.
+    pub fn check(&self, path: &Path, source: &[u8]) -> Result<Vec<Finding>, TabooError> {
+        let found = self.find(path, source)?;
+        Ok(self.check_found(path, source, &found)?.findings)
+    }
+
+    /// Parses `source`, the contents of the file at `path`, and finds where it may
+    /// have banned words, for [`Checker::check_found`] to sort out.
+    ///
+    /// This is the expensive half of [`Checker::check`]. What it finds can be kept, e.g.
+    /// in a cache keyed by the contents of the source, and sorted out again later.
+    pub fn find(&self, path: &Path, source: &[u8]) -> Result<Found, TabooError> {
+        let haskell = haskell_source(path, source);
+        PARSER.with_borrow_mut(|parser| {
+            let parser =
+                parser.get_or_insert_with(|| new_parser(&tree_sitter_haskell::LANGUAGE.into()));
+            self.scanner.find(parser, &haskell, path)
+        })
+    }
+
+    /// Sorts what [`Checker::find`] found in `source`, the contents of the file at
+    /// `path`, into the violations to report, the violations that are suppressed and
+    /// the near misses that exceptions permit.
+    pub fn check_found(
+        &self,
+        path: &Path,
+        source: &[u8],
+        found: &Found,
+    ) -> Result<Checked, TabooError> {
+        let haskell = haskell_source(path, source);
+        let scan = self.scanner.scan(&haskell, path, found)?;
+
+        let mut checked = Checked::default();
+        for violation in &scan.violations {
+            let finding = Finding::new(path, source, &violation.occurrence, &violation.matched);
+            match suppression(violation, path, source, scan.module, self.taboo_set) {
+                Some(suppression) => checked.suppressed.push((finding, suppression)),
+                None => checked.findings.push(finding),
+            }
+        }
+        checked.findings.sort_by_key(|finding| finding.span.start);
+        for NearMiss {
+            occurrence,
+            matched,
+            exception,
+        } in &scan.near_misses
+        {
+            let finding = Finding::new(path, source, occurrence, matched);
+            checked.near_misses.push((finding, exception.to_string()));
+        }
+        Ok(checked)
+    }
+}
+
+impl Finding {
+    fn new(path: &Path, source: &[u8], occurrence: &Occurrence, matched: &TabooMatch) -> Self {
+        let line_span = line_around(source, occurrence.start_byte, occurrence.end_byte);
+        let end = occurrence.end_position();
+        let matched_span = match matched.rule {
+            MatchRule::Substring { offset } => {
+                // substrings are found within the bare name, which ends the occurrence
+                let start = occurrence.end_byte - occurrence.name.len() + offset;
+                start..start + matched.word.len()
+            }
+            _ => occurrence.start_byte..occurrence.end_byte,
+        };
+        Finding {
+            path: path.to_path_buf(),
+            span: occurrence.start_byte..occurrence.end_byte,
+            line: occurrence.position.row + 1,
+            column: occurrence.position.column,
+            end_line: end.row + 1,
+            end_column: end.column,
+            matched_span,
+            identifier: occurrence.text.to_string(),
+            line_text: String::from_utf8_lossy(&source[line_span.clone()]).into_owned(),
+            line_span,
+            word: matched.word.to_string(),
+            canonical: matched.canonical.to_string(),
+            severity: matched.severity,
+            kind: matched.kind,
+            label: occurrence.label.map(str::to_string),
+            min_occurrences: matched.min_occurrences,
+            explanation: matched.explain(occurrence.text),
+            fix: edit_for(occurrence, matched),
+        }
+    }
+}
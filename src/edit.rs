@@ -0,0 +1,61 @@
+use crate::scanner::Occurrence;
+use crate::taboo::{EntryKind, MatchRule, TabooMatch, swap_leading_case, words};
+use std::ops::Range;
+
+/// A rewrite of the `bytes` of a source file to `text`.
+#[derive(Debug, Clone)]
+pub struct Edit {
+    pub bytes: Range<usize>,
+    pub text: String,
+}
+
+/// The edit replacing the part of `occurrence` that matched `matched` with the entry's
+/// replacement, if it has one and the occurrence is an identifier.
+///
+/// Only the matched part is replaced, so that a substring match of `whitelist` in
+/// `userWhitelist` becomes `userAllowlist`, and the replacement takes on the case of
+/// the first character it replaces when that differs from the entry's.
+pub fn edit_for(occurrence: &Occurrence, matched: &TabooMatch) -> Option<Edit> {
+    let replacement = matched.replacement?;
+    if !occurrence.identifier || !matches!(matched.kind, EntryKind::Literal) {
+        return None;
+    }
+
+    // the name ends the occurrence, after the module qualifying it, if any
+    let name_start = occurrence.end_byte - occurrence.name.len();
+    let (start, replaced) = match matched.rule {
+        MatchRule::Entry if same_ignoring_case(occurrence.text, matched.word) => {
+            (occurrence.start_byte, occurrence.text)
+        }
+        MatchRule::Entry => (name_start, occurrence.name),
+        MatchRule::Word { prime_is_word_char } => {
+            // an exact match first, in case another word only matches ignoring case
+            let (offset, word) = words(occurrence.name, prime_is_word_char)
+                .find(|(_, word)| *word == matched.word)
+                .or_else(|| {
+                    words(occurrence.name, prime_is_word_char)
+                        .find(|(_, word)| same_ignoring_case(word, matched.word))
+                })?;
+            (name_start + offset, word)
+        }
+        MatchRule::Substring { offset } => (
+            name_start + offset,
+            occurrence.name.get(offset..offset + matched.word.len())?,
+        ),
+    };
+
+    let starts_upper = |text: &str| text.chars().next().is_some_and(char::is_uppercase);
+    let text = if starts_upper(replaced) == starts_upper(matched.word) {
+        replacement.to_string()
+    } else {
+        swap_leading_case(replacement).unwrap_or_else(|| replacement.to_string())
+    };
+    Some(Edit {
+        bytes: start..start + replaced.len(),
+        text,
+    })
+}
+
+fn same_ignoring_case(a: &str, b: &str) -> bool {
+    a.to_lowercase() == b.to_lowercase()
+}
@@ -0,0 +1,222 @@
+use haskell_taboo::TabooError;
+use std::io;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+/// The ways that setting up a run and reporting on it can fail, beyond those of
+/// checking sources.
+#[derive(Debug, thiserror::Error)]
+pub enum CliError {
+    #[error(transparent)]
+    Taboo(#[from] TabooError),
+
+    #[error("No files given, and --no-default-dir disables scanning a default directory")]
+    NoFiles,
+
+    #[error("Failed to read {}/ directory: {source}", root.display())]
+    SourceDir {
+        root: PathBuf,
+        #[source]
+        source: ignore::Error,
+    },
+
+    /// A file named on the command line, or the directory a glob matches within, doesn't
+    /// exist.
+    #[error("{}: no such file or directory", path.display())]
+    MissingPath { path: PathBuf },
+
+    #[error("Invalid glob `{pattern}`: {source}")]
+    InvalidGlob {
+        pattern: String,
+        #[source]
+        source: globset::Error,
+    },
+
+    #[error("Invalid --exclude glob `{pattern}`: {source}")]
+    InvalidExclude {
+        pattern: String,
+        #[source]
+        source: ignore::Error,
+    },
+
+    /// A `.cabal` file, `package.yaml` or the directory holding them couldn't be read.
+    #[error("Error reading {}: {source}", path.display())]
+    ProjectFile {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+
+    #[error("Unsupported archive {}: expected .tar, .tar.gz, .tgz or .zip", path.display())]
+    UnsupportedArchive { path: PathBuf },
+
+    #[error("Error reading archive {}: {source}", path.display())]
+    Archive {
+        path: PathBuf,
+        #[source]
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+
+    #[error("Error reading exemptions file {}: {source}", path.display())]
+    Exemptions {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+
+    /// `--review` was run without a terminal to show its UI in, e.g. in CI.
+    #[error("--review needs stdin and stdout to be a terminal")]
+    NoTerminal,
+
+    #[error("Error setting up the terminal for --review: {source}")]
+    Terminal {
+        #[source]
+        source: io::Error,
+    },
+
+    #[error("Error writing baseline {}: {source}", path.display())]
+    Baseline {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+
+    #[error("Error creating output file {}: {source}", path.display())]
+    OutputFile {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+
+    #[error("Error running git to check for uncommitted changes: {source}")]
+    Git {
+        #[source]
+        source: io::Error,
+    },
+
+    /// `action` would rewrite files that git couldn't tell apart from earlier changes.
+    #[error("Refusing to {action} outside a git work tree or with uncommitted changes")]
+    DirtyTree { action: &'static str },
+
+    #[error("Error running git diff to find changed lines: {source}")]
+    GitDiff {
+        #[source]
+        source: io::Error,
+    },
+
+    /// git ran but couldn't diff, e.g. outside a work tree or for an unknown ref.
+    #[error("git diff failed: {message}")]
+    GitDiffFailed { message: String },
+
+    #[error("Error annotating {}: {source}", path.display())]
+    Annotate {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+
+    /// A file couldn't be read or rewritten by `--fix`, or its `--dry-run` diff written.
+    #[error("Error fixing {}: {source}", path.display())]
+    Fix {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+
+    #[error("--exec needs a command to run")]
+    EmptyExec,
+
+    /// Some of the commands run by `--exec` failed, after they all ran.
+    #[error("{failed} of {total} --exec command(s) failed")]
+    ExecFailed { failed: usize, total: usize },
+
+    /// The `--cache` file couldn't be written once the scan was done.
+    #[error("Error writing cache file {}: {source}", path.display())]
+    CacheFile {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+
+    #[error("Error reading file list {}: {source}", path.display())]
+    Manifest {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+
+    #[error("{}:{line}: {} is not a file", manifest.display(), path.display())]
+    MissingManifestEntry {
+        manifest: PathBuf,
+        line: usize,
+        path: PathBuf,
+    },
+
+    #[error("Error reading report {}: {source}", path.display())]
+    PreviousReport {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+
+    #[error("{} is not a JSON or NDJSON report: {source}", path.display())]
+    InvalidReport {
+        path: PathBuf,
+        #[source]
+        source: serde_json::Error,
+    },
+
+    /// A previous report was written with another version of the JSON schema.
+    #[error(
+        "{} has schema version {version}, but this version of the tool reads version {}",
+        path.display(),
+        crate::json::SCHEMA_VERSION
+    )]
+    IncompatibleReport { path: PathBuf, version: u32 },
+
+    /// A source file couldn't be read, which skips the file rather than stopping a scan.
+    #[error("{}: {source}", path.display())]
+    SourceFile {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+}
+
+impl CliError {
+    /// Whether the error is a problem with how the run was set up, e.g. a missing taboo
+    /// file or unreadable source directory, as opposed to a banned word being found.
+    pub fn is_config(&self) -> bool {
+        match self {
+            CliError::Taboo(e) => e.is_config(),
+            CliError::SourceFile { .. }
+            | CliError::ExecFailed { .. }
+            | CliError::CacheFile { .. }
+            | CliError::Annotate { .. }
+            | CliError::Fix { .. } => false,
+            _ => true,
+        }
+    }
+}
+
+/// Exit status when banned words were found.
+pub const EXIT_VIOLATIONS: u8 = 1;
+/// Exit status for usage and configuration errors, matching clap's usage errors.
+pub const EXIT_CONFIG: u8 = 2;
+/// Exit status for any other error, e.g. failing to write the report.
+pub const EXIT_INTERNAL: u8 = 3;
+
+/// Chooses the exit status for an error that stopped the run.
+pub fn exit_code_for(error: &anyhow::Error) -> ExitCode {
+    let is_config = match error.downcast_ref::<CliError>() {
+        Some(e) => e.is_config(),
+        None => error
+            .downcast_ref::<TabooError>()
+            .is_some_and(TabooError::is_config),
+    };
+    if is_config {
+        ExitCode::from(EXIT_CONFIG)
+    } else {
+        ExitCode::from(EXIT_INTERNAL)
+    }
+}
@@ -0,0 +1,69 @@
+use crate::error::CliError;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// The placeholder in an `--exec` command that is replaced by each file's path.
+pub const PATH_PLACEHOLDER: &str = "{}";
+
+/// Runs `command` once for each of `paths`, like `find -exec`, writing the exit status
+/// of each invocation to `out` and returning how many of them failed.
+///
+/// `command` is split on whitespace, without a shell, and every `{}` in it is replaced
+/// by the path. Without a `{}`, the path is passed as the last argument. The commands'
+/// own output goes to stderr, so that it can't be mistaken for a report on stdout.
+/// `command` must not be blank, which [`check_command`] ensures.
+pub fn exec_for_each(
+    command: &str,
+    paths: &[PathBuf],
+    out: &mut impl Write,
+) -> anyhow::Result<usize> {
+    let words: Vec<&str> = command.split_whitespace().collect();
+
+    let mut failed = 0;
+    for path in paths {
+        let argv = substitute(&words, path);
+        let status = Command::new(&argv[0])
+            .args(&argv[1..])
+            .stdin(Stdio::null())
+            .stdout(io::stderr())
+            .status();
+
+        let outcome = match status {
+            Ok(status) if status.success() => "exited with status 0".to_string(),
+            Ok(status) => {
+                failed += 1;
+                match status.code() {
+                    Some(code) => format!("exited with status {}", code),
+                    None => "was killed by a signal".to_string(),
+                }
+            }
+            Err(e) => {
+                failed += 1;
+                format!("couldn't be run: {}", e)
+            }
+        };
+        writeln!(out, "exec: `{}` {}", argv.join(" "), outcome)?;
+    }
+    Ok(failed)
+}
+
+/// Fails if `command` has nothing to run, before any scanning is done.
+pub fn check_command(command: &str) -> Result<(), CliError> {
+    if command.trim().is_empty() {
+        return Err(CliError::EmptyExec);
+    }
+    Ok(())
+}
+
+fn substitute(words: &[&str], path: &Path) -> Vec<String> {
+    let path = path.to_string_lossy();
+    let mut argv: Vec<String> = words
+        .iter()
+        .map(|word| word.replace(PATH_PLACEHOLDER, &path))
+        .collect();
+    if !words.iter().any(|word| word.contains(PATH_PLACEHOLDER)) {
+        argv.push(path.into_owned());
+    }
+    argv
+}
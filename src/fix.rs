@@ -0,0 +1,108 @@
+use crate::error::CliError;
+use haskell_taboo::Edit;
+use std::io::Write;
+use std::ops::Range;
+use std::path::Path;
+
+/// Lines of unchanged context around each change in a `--dry-run` diff.
+const DIFF_CONTEXT_LINES: usize = 3;
+
+/// Makes `edits` to the file at `path`, returning how many were made, or with `diff`,
+/// writes them to it as a unified diff instead of changing the file.
+///
+/// Edits overlapping an earlier one, like those of the same identifier captured by
+/// two queries, are left out.
+pub fn fix_file(
+    path: &Path,
+    edits: &[Edit],
+    diff: Option<&mut impl Write>,
+) -> Result<usize, CliError> {
+    let fix_error = |source| CliError::Fix {
+        path: path.to_path_buf(),
+        source,
+    };
+    let source = std::fs::read(path).map_err(fix_error)?;
+
+    let mut edits: Vec<&Edit> = edits.iter().collect();
+    edits.sort_by_key(|edit| edit.bytes.start);
+    let mut fixed = Vec::with_capacity(source.len());
+    let mut cursor = 0;
+    let mut made = 0;
+    for edit in edits {
+        if edit.bytes.start < cursor {
+            continue;
+        }
+        fixed.extend_from_slice(&source[cursor..edit.bytes.start]);
+        fixed.extend_from_slice(edit.text.as_bytes());
+        cursor = edit.bytes.end;
+        made += 1;
+    }
+    fixed.extend_from_slice(&source[cursor..]);
+
+    match diff {
+        Some(out) => write_diff(out, path, &source, &fixed),
+        None => std::fs::write(path, &fixed),
+    }
+    .map_err(fix_error)?;
+    Ok(made)
+}
+
+/// Writes a unified diff of the file at `path` from `old` to `new`, which have the same
+/// number of lines, since edits never add or remove line breaks.
+fn write_diff(out: &mut impl Write, path: &Path, old: &[u8], new: &[u8]) -> std::io::Result<()> {
+    let old_lines: Vec<&[u8]> = old.split_inclusive(|b| *b == b'\n').collect();
+    let new_lines: Vec<&[u8]> = new.split_inclusive(|b| *b == b'\n').collect();
+    let changed: Vec<usize> = (0..old_lines.len())
+        .filter(|&line| old_lines[line] != new_lines[line])
+        .collect();
+    if changed.is_empty() {
+        return Ok(());
+    }
+
+    // changes close enough for their context to touch share a hunk
+    let mut hunks: Vec<Range<usize>> = Vec::new();
+    for &line in &changed {
+        let start = line.saturating_sub(DIFF_CONTEXT_LINES);
+        let end = (line + 1 + DIFF_CONTEXT_LINES).min(old_lines.len());
+        match hunks.last_mut() {
+            Some(hunk) if start <= hunk.end => hunk.end = end,
+            _ => hunks.push(start..end),
+        }
+    }
+
+    writeln!(out, "--- {}", path.display())?;
+    writeln!(out, "+++ {}", path.display())?;
+    for hunk in hunks {
+        let range = format!("{},{}", hunk.start + 1, hunk.len());
+        writeln!(out, "@@ -{range} +{range} @@")?;
+
+        let mut line = hunk.start;
+        while line < hunk.end {
+            if old_lines[line] == new_lines[line] {
+                write_line(out, b' ', old_lines[line])?;
+                line += 1;
+                continue;
+            }
+            let run_end = (line..hunk.end)
+                .find(|&l| old_lines[l] == new_lines[l])
+                .unwrap_or(hunk.end);
+            for old_line in &old_lines[line..run_end] {
+                write_line(out, b'-', old_line)?;
+            }
+            for new_line in &new_lines[line..run_end] {
+                write_line(out, b'+', new_line)?;
+            }
+            line = run_end;
+        }
+    }
+    Ok(())
+}
+
+fn write_line(out: &mut impl Write, prefix: u8, line: &[u8]) -> std::io::Result<()> {
+    out.write_all(&[prefix])?;
+    out.write_all(line)?;
+    if !line.ends_with(b"\n") {
+        out.write_all(b"\n\\ No newline at end of file\n")?;
+    }
+    Ok(())
+}
@@ -0,0 +1,365 @@
+use crate::error::CliError;
+use haskell_taboo::{EntryKind, Severity};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::path::Path;
+use tree_sitter::Point;
+
+/// The version of the JSON schema, bumped whenever a field is removed or renamed or
+/// changes meaning. New fields may be added without bumping it.
+///
+/// `--format json` writes one object once the scan finishes:
+///
+/// ```text
+/// {
+///   "version": 1,
+///   "tool": { "name": "haskell-taboo", "version": "0.1.0" },
+///   "violations": [
+///     {
+///       "path": "src/Foo.hs",
+///       "line": 3,            // 1-based
+///       "column": 4,          // as in the human report, per --tab-width
+///       "start_byte": 31,     // the 0-based byte span of `text` in the file
+///       "end_byte": 36,
+///       "text": "Q.foo",      // the source text that matched
+///       "word": "foo",        // the banned entry it matched
+///       "canonical": "foo",   // the entry's alias group, or the entry itself
+///       "severity": "error",  // or "warning"
+///       "kind": "literal",    // or "prefix" or "pattern"
+///       "label": null,        // or the capture name, like "pragma" or "TH"
+///       "fingerprint": "9f2c4e0a1b3d5c7e"
+///     }
+///   ],
+///   "summary": { "errors": 1, "warnings": 0, "files": 1 }
+/// }
+/// ```
+///
+/// `--format ndjson` writes the same information as one object per line as the scan
+/// goes, each tagged with its `"type"`: a `header` with the version and tool, then a
+/// `violation` for each violation, then a `summary`.
+///
+/// A violation's `fingerprint` identifies it across runs while lines are added and
+/// removed around it. See [`fingerprint`].
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// How JSON output identifies the program that wrote it.
+#[derive(Debug, Serialize)]
+pub struct Tool {
+    pub name: &'static str,
+    pub version: &'static str,
+}
+
+/// The name and version that `--version` reports.
+pub const TOOL: Tool = Tool {
+    name: env!("CARGO_PKG_NAME"),
+    version: env!("CARGO_PKG_VERSION"),
+};
+
+#[derive(Debug, Serialize)]
+pub struct Finding {
+    pub path: String,
+    pub line: usize,
+    pub column: usize,
+    pub start_byte: usize,
+    pub end_byte: usize,
+    pub text: String,
+    pub word: String,
+    pub canonical: String,
+    pub severity: Severity,
+    pub kind: EntryKind,
+    pub label: Option<String>,
+    pub fingerprint: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Totals {
+    pub errors: usize,
+    pub warnings: usize,
+    pub files: usize,
+}
+
+#[derive(Serialize)]
+struct Report<'a> {
+    version: u32,
+    tool: &'a Tool,
+    violations: &'a [Finding],
+    summary: &'a Totals,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+enum Record<'a> {
+    Header { version: u32, tool: &'a Tool },
+    Violation(&'a Finding),
+    Summary(&'a Totals),
+}
+
+/// Writes the whole `--format json` report.
+pub fn write_report(out: &mut dyn Write, findings: &[Finding], totals: &Totals) -> io::Result<()> {
+    let report = Report {
+        version: SCHEMA_VERSION,
+        tool: &TOOL,
+        violations: findings,
+        summary: totals,
+    };
+    serde_json::to_writer_pretty(&mut *out, &report)?;
+    writeln!(out)
+}
+
+/// Writes the `--format ndjson` line that precedes every violation.
+pub fn write_header(out: &mut dyn Write) -> io::Result<()> {
+    write_record(
+        out,
+        &Record::Header {
+            version: SCHEMA_VERSION,
+            tool: &TOOL,
+        },
+    )
+}
+
+pub fn write_finding(out: &mut dyn Write, finding: &Finding) -> io::Result<()> {
+    write_record(out, &Record::Violation(finding))
+}
+
+pub fn write_totals(out: &mut dyn Write, totals: &Totals) -> io::Result<()> {
+    write_record(out, &Record::Summary(totals))
+}
+
+fn write_record(out: &mut dyn Write, record: &Record) -> io::Result<()> {
+    serde_json::to_writer(&mut *out, record)?;
+    writeln!(out)
+}
+
+/// A violation as a diagnostic of reviewdog's JSON format, rdjson, for `--format rdjson`.
+#[derive(Debug, Serialize)]
+pub struct Diagnostic {
+    message: String,
+    location: Location,
+    severity: &'static str,
+    code: Code,
+}
+
+#[derive(Debug, Serialize)]
+struct Location {
+    path: String,
+    range: Range,
+}
+
+#[derive(Debug, Serialize)]
+struct Range {
+    start: Position,
+    end: Position,
+}
+
+/// A 1-based line and column, where the column counts UTF-8 bytes.
+#[derive(Debug, Serialize)]
+struct Position {
+    line: usize,
+    column: usize,
+}
+
+impl From<Point> for Position {
+    fn from(point: Point) -> Self {
+        Position {
+            line: point.row + 1,
+            column: point.column + 1,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct Code {
+    value: String,
+}
+
+impl Diagnostic {
+    /// Describes `finding`, which spans from `start` to `end`, with `explanation` of how
+    /// it matched.
+    pub fn new(finding: &Finding, explanation: &str, start: Point, end: Point) -> Self {
+        Diagnostic {
+            message: format!("banned identifier `{}`: {}", finding.text, explanation),
+            location: Location {
+                path: finding.path.clone(),
+                range: Range {
+                    start: start.into(),
+                    end: end.into(),
+                },
+            },
+            severity: match finding.severity {
+                Severity::Error => "ERROR",
+                Severity::Warning => "WARNING",
+            },
+            code: Code {
+                value: finding.word.clone(),
+            },
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct RdjsonSource {
+    name: &'static str,
+}
+
+#[derive(Serialize)]
+struct DiagnosticResult<'a> {
+    source: RdjsonSource,
+    diagnostics: &'a [Diagnostic],
+}
+
+/// Writes the whole `--format rdjson` report, which reviewdog reads with `-f=rdjson`.
+pub fn write_rdjson(out: &mut dyn Write, diagnostics: &[Diagnostic]) -> io::Result<()> {
+    let result = DiagnosticResult {
+        source: RdjsonSource { name: TOOL.name },
+        diagnostics,
+    };
+    serde_json::to_writer_pretty(&mut *out, &result)?;
+    writeln!(out)
+}
+
+/// Hashes the reported path, matched entry, matched text and trimmed line of a
+/// violation, but not its line number or column, so that it only changes when the
+/// violation itself does.
+///
+/// This is FNV-1a rather than a std hasher, whose output may change between releases.
+pub fn fingerprint(path: &str, word: &str, text: &str, line: &[u8]) -> String {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for part in [
+        path.as_bytes(),
+        word.as_bytes(),
+        text.as_bytes(),
+        line.trim_ascii(),
+    ] {
+        // 0xff, which UTF-8 never uses, marks where each part ends
+        for byte in part.iter().chain([&0xff]) {
+            hash ^= u64::from(*byte);
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+    format!("{hash:016x}")
+}
+
+/// A violation of an earlier report.
+#[derive(Debug, Deserialize)]
+pub struct PreviousFinding {
+    pub path: String,
+    pub line: usize,
+    pub word: String,
+    /// Missing from reports written before fingerprints were added.
+    fingerprint: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct PreviousJson {
+    version: u32,
+    violations: Vec<PreviousFinding>,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+enum PreviousRecord {
+    Header {
+        version: u32,
+    },
+    Violation(PreviousFinding),
+    #[serde(other)]
+    Other,
+}
+
+/// The violations of an earlier `--format json` or `--format ndjson` report, for
+/// `--compare-to` and `--baseline`.
+#[derive(Debug, Default)]
+pub struct PreviousReport {
+    findings: Vec<PreviousFinding>,
+    /// The indices into `findings` of the violations with each fingerprint that no
+    /// current violation has taken yet, so that a file with one more copy of a line
+    /// than before still gets the extra copy reported.
+    fingerprints: HashMap<String, Vec<usize>>,
+    /// The same for violations without a fingerprint, keyed by `path:line:word`.
+    locations: HashMap<String, Vec<usize>>,
+}
+
+impl PreviousReport {
+    pub fn load(path: &Path) -> Result<Self, CliError> {
+        let contents =
+            std::fs::read_to_string(path).map_err(|source| CliError::PreviousReport {
+                path: path.to_path_buf(),
+                source,
+            })?;
+
+        let (version, findings) = match serde_json::from_str::<PreviousJson>(&contents) {
+            Ok(report) => (report.version, report.violations),
+            // a file that isn't NDJSON either is most likely broken JSON
+            Err(json_error) => parse_ndjson(&contents).ok_or(CliError::InvalidReport {
+                path: path.to_path_buf(),
+                source: json_error,
+            })?,
+        };
+        if version != SCHEMA_VERSION {
+            return Err(CliError::IncompatibleReport {
+                path: path.to_path_buf(),
+                version,
+            });
+        }
+
+        let mut previous = PreviousReport::default();
+        for (index, finding) in findings.iter().enumerate() {
+            let untaken = match &finding.fingerprint {
+                Some(fingerprint) => previous.fingerprints.entry(fingerprint.clone()),
+                None => {
+                    previous
+                        .locations
+                        .entry(location(&finding.path, finding.line, &finding.word))
+                }
+            };
+            untaken.or_default().push(index);
+        }
+        previous.findings = findings;
+        Ok(previous)
+    }
+
+    /// Whether a violation was in the previous report, discounting it so that each
+    /// previous violation stands for at most one current one.
+    pub fn take(&mut self, fingerprint: &str, path: &str, line: usize, word: &str) -> bool {
+        let untaken = match self.fingerprints.get_mut(fingerprint) {
+            Some(untaken) => Some(untaken),
+            None => self.locations.get_mut(&location(path, line, word)),
+        };
+        untaken.is_some_and(|untaken| untaken.pop().is_some())
+    }
+
+    /// The previous violations that no current violation took, in report order.
+    pub fn untaken(&self) -> Vec<&PreviousFinding> {
+        let mut untaken: Vec<usize> = self
+            .fingerprints
+            .values()
+            .chain(self.locations.values())
+            .flatten()
+            .copied()
+            .collect();
+        untaken.sort_unstable();
+        untaken
+            .into_iter()
+            .map(|index| &self.findings[index])
+            .collect()
+    }
+}
+
+fn location(path: &str, line: usize, word: &str) -> String {
+    format!("{path}:{line}:{word}")
+}
+
+/// Parses an NDJSON report into its version and violations.
+fn parse_ndjson(contents: &str) -> Option<(u32, Vec<PreviousFinding>)> {
+    let mut version = None;
+    let mut findings = Vec::new();
+    for line in contents.lines().filter(|line| !line.trim().is_empty()) {
+        match serde_json::from_str(line).ok()? {
+            PreviousRecord::Header { version: v } => version = Some(v),
+            PreviousRecord::Violation(finding) => findings.push(finding),
+            PreviousRecord::Other => {}
+        }
+    }
+    Some((version?, findings))
+}
@@ -0,0 +1,92 @@
+//! Checks Haskell sources for banned identifiers, as the `haskell-taboo` command does,
+//! for tools that would rather have what's found as values than as a report.
+//!
+//! ```no_run
+//! use haskell_taboo::{CheckOptions, Checker, load_taboo_file};
+//! use std::path::Path;
+//!
+//! let taboo_set = load_taboo_file(Path::new("taboo.txt"))?;
+//! let checker = Checker::new(&taboo_set, &CheckOptions::default())?;
+//! let path = Path::new("src/Main.hs");
+//! for finding in checker.check(path, &std::fs::read(path)?)? {
+//!     println!("{}:{}: {}", finding.path.display(), finding.line, finding.word);
+//! }
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+//!
+//! A [`Checker`] is what to keep around between scans, e.g. in watch mode or an editor
+//! integration: [`Checker::new`] compiles the queries of a banned words list once, and
+//! every source is then checked against them with [`Checker::check`], or with
+//! [`Checker::find`] and [`Checker::check_found`] to keep what was found, as a cache of
+//! parsed files would. The scanner it wraps isn't public, since what it finds borrows
+//! from both the source and the list, where a checker's [`Finding`]s own everything
+//! they describe.
+
+mod checker;
+mod edit;
+mod literate;
+mod pattern;
+mod scanner;
+mod suppress;
+mod taboo;
+mod trie;
+
+use std::io;
+use std::path::PathBuf;
+use std::str::Utf8Error;
+
+pub use checker::{CheckOptions, Checked, Checker, Finding};
+pub use edit::Edit;
+pub use scanner::{Found, IgnoreIn, Kind, Only, ScanOptions};
+pub use suppress::{ALLOW_MARKER, Suppression};
+pub use taboo::{
+    EntryKind, MatchOptions, Severity, TabooLint, TabooSet, banned_words_from, lint_taboo_file,
+    load_taboo_file,
+};
+
+/// The ways that loading a banned words list and checking sources can fail.
+#[derive(Debug, thiserror::Error)]
+pub enum TabooError {
+    #[error("Error reading taboo file {}: {source}", path.display())]
+    TabooFile {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+
+    #[error("Error constructing query `{query}`: {source}")]
+    InvalidQuery {
+        query: String,
+        #[source]
+        source: tree_sitter::QueryError,
+    },
+
+    /// Parsing a source file ran past [`ScanOptions::timeout`].
+    #[error("{}: parsing took longer than {timeout_ms}ms", path.display())]
+    ParseTimeout { path: PathBuf, timeout_ms: u64 },
+
+    /// A source file couldn't be parsed as Haskell at all.
+    #[error("{}: couldn't be parsed", path.display())]
+    Parse { path: PathBuf },
+
+    /// An identifier in a source file wasn't valid UTF-8.
+    #[error("{}: {source}", path.display())]
+    InvalidUtf8 {
+        path: PathBuf,
+        #[source]
+        source: Utf8Error,
+    },
+}
+
+impl TabooError {
+    /// Whether the error is a problem with the banned words list, as opposed to with one
+    /// of the sources being checked.
+    pub fn is_config(&self) -> bool {
+        !matches!(
+            self,
+            TabooError::ParseTimeout { .. }
+                | TabooError::Parse { .. }
+                | TabooError::InvalidUtf8 { .. }
+        )
+    }
+}
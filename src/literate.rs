@@ -0,0 +1,51 @@
+use std::borrow::Cow;
+use std::path::Path;
+
+/// Returns the Haskell code to parse for the file at `path` holding `source`, which is
+/// `source` itself unless the file is literate Haskell ending in `.lhs`.
+pub fn haskell_source<'a>(path: &Path, source: &'a [u8]) -> Cow<'a, [u8]> {
+    if path.extension().is_some_and(|ext| ext == "lhs") {
+        Cow::Owned(unlit(source))
+    } else {
+        Cow::Borrowed(source)
+    }
+}
+
+/// Blanks out everything in a literate Haskell file but its code, which is either on
+/// lines starting with `>` (bird tracks) or between `\begin{code}` and `\end{code}`.
+///
+/// Every byte but line breaks is kept or replaced by a space, bird tracks included, so
+/// that the offsets, lines and columns of the code stay those of the original file.
+fn unlit(source: &[u8]) -> Vec<u8> {
+    let mut code = Vec::with_capacity(source.len());
+    let mut in_code_block = false;
+
+    for line in source.split_inclusive(|b| *b == b'\n') {
+        let content = line.strip_suffix(b"\n").unwrap_or(line);
+        let content = content.strip_suffix(b"\r").unwrap_or(content);
+        let line_break = &line[content.len()..];
+
+        if in_code_block {
+            if content.starts_with(b"\\end{code}") {
+                in_code_block = false;
+                blank(&mut code, content);
+            } else {
+                code.extend_from_slice(content);
+            }
+        } else if content.starts_with(b"\\begin{code}") {
+            in_code_block = true;
+            blank(&mut code, content);
+        } else if let Some(rest) = content.strip_prefix(b">") {
+            code.push(b' ');
+            code.extend_from_slice(rest);
+        } else {
+            blank(&mut code, content);
+        }
+        code.extend_from_slice(line_break);
+    }
+    code
+}
+
+fn blank(code: &mut Vec<u8>, content: &[u8]) {
+    code.resize(code.len() + content.len(), b' ');
+}
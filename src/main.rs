@@ -1,143 +1,1897 @@
-use anyhow::Context;
-use clap::Parser;
+use clap::{ArgAction, Parser};
 use colored::Colorize;
 use memmap2::Mmap;
-use std::collections::HashSet;
+use serde::Serialize;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
 use std::fs::File;
-use std::io::{BufRead, BufReader};
-use std::path::PathBuf;
+use std::io::{IsTerminal, Read, Write};
+use std::num::NonZeroUsize;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
 use std::process::ExitCode;
-use std::fs;
-use tree_sitter::{Query, QueryCursor, StreamingIterator};
+use std::time::{Duration, Instant};
+use tree_sitter::Point;
 
-#[derive(Parser, Debug)]
-#[command(version, about, long_about = None)]
+mod annotate;
+mod archive;
+mod cache;
+mod changes;
+mod error;
+mod exec;
+mod fix;
+mod json;
+mod paths;
+mod review;
+mod sarif;
+
+use annotate::{annotate_file, require_clean_tree};
+use archive::haskell_entries;
+use cache::Cache;
+use changes::ChangedLines;
+use error::{CliError, EXIT_VIOLATIONS, exit_code_for};
+use exec::{check_command, exec_for_each};
+use fix::fix_file;
+use haskell_taboo::{
+    CheckOptions, Checked, Checker, Edit, Finding, Found, IgnoreIn, Kind, MatchOptions, Only,
+    ScanOptions, Severity, Suppression, TabooError, TabooSet, lint_taboo_file, load_taboo_file,
+};
+use json::{Diagnostic, PreviousReport, Totals, fingerprint};
+use paths::{
+    cabal_source_dirs, git_root, glob_files, hpack_source_dirs, is_glob, manifest_files,
+    relative_to, walk_files,
+};
+use rayon::prelude::*;
+use regex::Regex;
+use review::{CONTEXT_LINES, Exemptions, ReviewItem, review};
+use sarif::{SarifResult, write_sarif};
+
+#[derive(Parser, Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+#[command(
+    version,
+    about,
+    long_about = None,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true,
+    after_help = "Exit status: 0 if no banned words were found, 1 if some were, 2 for usage and \
+                  configuration errors, 3 for any other error.\n\n\
+                  Reports for people are written to stderr. Only --format output, --dry-run \
+                  diffs and the --count-only total are written to stdout."
+)]
 struct Args {
+    #[command(subcommand)]
+    #[serde(skip)]
+    command: Option<Command>,
+
     /// Location of banned words list
-    taboo: String,
+    #[arg(required = true)]
+    taboo: Option<String>,
 
-    /// Files to check against
+    /// Files to check against, where `-` reads Haskell source from stdin
+    ///
+    /// Directories are searched recursively for `.hs` and `.lhs` files, skipping
+    /// anything ignored by git, and glob patterns like `'app/**/*.hs'` are expanded
+    /// the same way.
     files: Vec<String>,
+
+    /// Skip directories and files matching this gitignore-style glob, like
+    /// `dist-newstyle`, when searching directories and expanding globs
+    ///
+    /// Can be given more than once. Files named directly are always checked.
+    #[arg(long, value_name = "GLOB")]
+    exclude: Vec<String>,
+
+    /// Report source read from stdin under this path, which also decides the `in`
+    /// annotations, local taboo files and git root that apply to it
+    ///
+    /// Editors passing the contents of an unsaved buffer can use this to get locations
+    /// pointing at the file being edited. Without any files, stdin is read as if `-`
+    /// were given.
+    #[arg(long, value_name = "PATH")]
+    stdin_filename: Option<PathBuf>,
+
+    /// Directory to scan when no files are given, instead of discovering one
+    ///
+    /// Without this, the `source-dirs` of a `package.yaml` or the `hs-source-dirs`
+    /// of a `.cabal` file in the current directory are scanned, or the directory itself
+    /// for components without them, falling back to `src` without either file.
+    #[arg(long, value_name = "DIR")]
+    default_dir: Option<PathBuf>,
+
+    /// Fail instead of scanning a default directory when no files are given
+    #[arg(long, conflicts_with = "default_dir")]
+    no_default_dir: bool,
+
+    /// Check this Haskell source instead of any files, reported as `<inline>`
+    #[arg(long, value_name = "CODE", conflicts_with = "files")]
+    source: Option<String>,
+
+    /// Also check every file listed in FILE, one path per line relative to FILE's
+    /// directory, such as the modules a build generates with alex or happy
+    ///
+    /// Blank lines and lines starting with `#` are skipped, and every listed file must
+    /// exist. No default directory is scanned when a list is given.
+    #[arg(long, value_name = "FILE")]
+    files_from: Option<PathBuf>,
+
+    /// Also check the Haskell files inside this .tar, .tar.gz, .tgz or .zip archive,
+    /// reporting them by their names in the archive
+    #[arg(long, value_name = "FILE")]
+    archive: Option<PathBuf>,
+
+    /// Also apply the `.taboo` files in the directories containing each scanned file
+    ///
+    /// Each file is checked against the `.taboo` files found by walking up from its
+    /// directory, stopping at the current directory for relative paths.
+    #[arg(long, value_enum, value_name = "MODE")]
+    local_taboo: Option<LocalTaboo>,
+
+    /// Skip files whose first lines match --generated-marker
+    #[arg(long)]
+    skip_generated: bool,
+
+    /// Regex marking a file as generated when it matches one of its first 10 lines
+    #[arg(
+        long,
+        value_name = "REGEX",
+        default_value = "(?i)do not edit|@generated"
+    )]
+    #[serde(serialize_with = "serialize_regex")]
+    generated_marker: Regex,
+
+    /// Write the report to this file instead of stdout/stderr
+    #[arg(long, value_name = "FILE")]
+    output: Option<PathBuf>,
+
+    /// When to highlight banned words with color
+    ///
+    /// `auto` colors output to a terminal, unless `NO_COLOR` is set or `CLICOLOR` is
+    /// `0`. Setting `CLICOLOR_FORCE` to anything but `0` colors it regardless, for CI
+    /// logs that render ANSI colors without being a terminal.
+    #[arg(long, value_enum, value_name = "WHEN", default_value_t = ColorChoice::Auto)]
+    color: ColorChoice,
+
+    /// The same as `--color always`
+    #[arg(long, conflicts_with = "color")]
+    force_color: bool,
+
+    /// Only report banned words at these sites
+    #[arg(long, value_enum)]
+    only: Option<Only>,
+
+    /// Tag each reported line with the banned word it matched, e.g. `[foo]`
+    #[arg(long)]
+    show_word: bool,
+
+    /// Print only the total number of violations
+    #[arg(long)]
+    count_only: bool,
+
+    /// Also print how many violations were suppressed, and by what
+    #[arg(long)]
+    show_suppressed: bool,
+
+    /// Measure scanning throughput instead of reporting violations
+    #[arg(long)]
+    bench: bool,
+
+    /// Exit successfully even when banned words are found
+    #[arg(long)]
+    no_fail: bool,
+
+    /// After scanning, report the violations found in this form instead of per line
+    #[arg(long, value_enum, value_name = "FORM")]
+    report: Option<Report>,
+
+    /// Report a banned word only once it has at least N violations, unless its entry
+    /// sets its own count with `:: min N`
+    #[arg(long, value_name = "N")]
+    min_occurrences: Option<usize>,
+
+    /// Whether `--min-occurrences` and `:: min` count violations across every file or
+    /// within each file
+    #[arg(long, value_enum, value_name = "SCOPE", default_value_t = OccurrenceScope::Run)]
+    occurrence_scope: OccurrenceScope,
+
+    /// Write violations as JSON or SARIF instead of the human-readable report
+    ///
+    /// Also accepted as `--output-format`.
+    #[arg(
+        long,
+        alias = "output-format",
+        value_enum,
+        value_name = "FORMAT",
+        conflicts_with_all = ["count_only", "bench", "review", "annotate", "report", "report_unused"]
+    )]
+    format: Option<Format>,
+
+    /// Report only the violations missing from this earlier `--format json` or
+    /// `--format ndjson` report, matched by their fingerprints
+    ///
+    /// The exit status then reflects only the new violations.
+    #[arg(long, value_name = "REPORT")]
+    compare_to: Option<PathBuf>,
+
+    /// Write every violation found to FILE as a baseline for --baseline, instead of
+    /// reporting them
+    ///
+    /// The baseline is a `--format json` report, so it can be read by --compare-to too.
+    #[arg(
+        long,
+        value_name = "FILE",
+        conflicts_with_all = ["format", "count_only", "bench", "review", "annotate", "fix", "baseline"]
+    )]
+    write_baseline: Option<PathBuf>,
+
+    /// Ignore the violations recorded in FILE by --write-baseline, so that only new
+    /// violations are reported and fail the run
+    ///
+    /// Violations are matched like --compare-to matches them, by a fingerprint of their
+    /// file, identifier and line that doesn't change as lines move. Recorded violations
+    /// that are gone are listed, so that writing the baseline again can drop them.
+    #[arg(long, value_name = "FILE", conflicts_with = "compare_to")]
+    baseline: Option<PathBuf>,
+
+    /// Report only the violations on lines changed since the current branch forked
+    /// from REF, as `git diff --merge-base REF` shows them, uncommitted changes included
+    ///
+    /// Whole files are still parsed, so only what's reported changes: thresholds,
+    /// baselines and --compare-to still see every violation. Untracked files, archive
+    /// entries and inline source have no changed lines.
+    #[arg(long, value_name = "REF", conflicts_with = "write_baseline")]
+    changed_since: Option<String>,
+
+    /// Report only the violations on lines changed in the index, as `git diff --cached`
+    /// shows them, or with --changed-since, staged changes since the branch forked
+    ///
+    /// Lines are numbered as in the staged version of a file, which is the one on disk
+    /// unless it also has unstaged changes.
+    #[arg(long, conflicts_with = "write_baseline")]
+    staged: bool,
+
+    /// The most example locations listed for each word in a grouped report
+    #[arg(long, value_name = "K", default_value_t = 3)]
+    examples: usize,
+
+    /// After scanning, list the banned entries that were never reported
+    #[arg(long)]
+    report_unused: bool,
+
+    /// Append `-- taboo:allow` to every line with a violation, permitting them from then
+    /// on, instead of reporting them. Requires the files annotated to be in git work
+    /// trees without uncommitted changes to them
+    #[arg(long, conflicts_with_all = ["count_only", "bench", "review"])]
+    annotate: bool,
+
+    /// Step through the violations found in a terminal UI instead of reporting them,
+    /// writing each one allowed to the exemptions file
+    #[arg(long, conflicts_with_all = ["count_only", "bench"])]
+    review: bool,
+
+    /// Rewrite the violations of entries with a replacement, like `whitelist =>
+    /// allowlist`, to the replacement, reporting only the violations left. The files
+    /// rewritten have the same requirements as with --annotate
+    ///
+    /// Only identifiers are rewritten, never the words of comments, strings or pragmas.
+    #[arg(long, conflicts_with_all = ["annotate", "review", "bench"])]
+    fix: bool,
+
+    /// With --fix, print the rewrites as a unified diff instead of making them, still
+    /// reporting the violations they would fix
+    #[arg(long, requires = "fix", conflicts_with = "format")]
+    dry_run: bool,
+
+    /// After reporting, run CMD once for each file with a violation, replacing `{}` in
+    /// it with the file's path, or passing the path last if there is no `{}`
+    ///
+    /// CMD is split on whitespace and run without a shell, e.g. `--exec 'ormolu -i {}'`.
+    /// Only files on disk are passed, never stdin or archive entries. The exit status of
+    /// every command is reported, and the run fails if any of them did.
+    #[arg(long, value_name = "CMD", conflicts_with_all = ["annotate", "review", "bench"])]
+    exec: Option<String>,
+
+    /// Violations to ignore, one `path:line:identifier` per line, as written by --review
+    ///
+    /// Without it, only --review reads and writes exemptions, in `.taboo-exemptions`.
+    #[arg(long, value_name = "FILE")]
+    exemptions: Option<PathBuf>,
+
+    /// Fail only if more than this many error-severity violations are found
+    #[arg(long, default_value_t = 0)]
+    max_errors: usize,
+
+    /// Fail if more than this many warning-severity violations are found [default: unlimited]
+    #[arg(long)]
+    max_warnings: Option<usize>,
+
+    /// Also check the words of LANGUAGE, OPTIONS_GHC and other pragmas
+    #[arg(long)]
+    include_pragmas: bool,
+
+    /// The kinds of identifiers that entries outside of `[query]` and `[kinds]` sections
+    /// are checked against
+    ///
+    /// Each kind but variables and operators is labelled in reports, like `[type]`.
+    #[arg(
+        long,
+        value_enum,
+        value_delimiter = ',',
+        default_values_t = Kind::DEFAULT,
+        conflicts_with = "ban_modules"
+    )]
+    kinds: Vec<Kind>,
+
+    /// Never report matches in these contexts, even when a query captures them
+    #[arg(long, value_enum, value_delimiter = ',')]
+    ignore_in: Vec<IgnoreIn>,
+
+    /// Print the effective configuration as JSON and exit without scanning, including
+    /// the sources that would be scanned and the taboo files applying to each
+    #[arg(long)]
+    #[serde(skip)]
+    config_dump: bool,
+
+    /// Print each offending line once, noting how many occurrences it contains
+    #[arg(long)]
+    unique_lines: bool,
+
+    /// Give up parsing any file that takes longer than this many milliseconds
+    #[arg(long, value_name = "MS")]
+    timeout_per_file: Option<u64>,
+
+    /// Parse this many files at once, instead of one per CPU
+    #[arg(short, long, value_name = "N")]
+    jobs: Option<NonZeroUsize>,
+
+    /// Remember what was found in each file in FILE, and skip parsing files whose
+    /// contents haven't changed since with the same taboo files and options
+    #[arg(long, value_name = "FILE")]
+    cache: Option<PathBuf>,
+
+    /// Annotate each violation with the rule that reported it and its severity
+    #[arg(long)]
+    explain: bool,
+
+    /// With --explain, also list identifiers that matched a ban but were excepted
+    #[arg(long, requires = "explain")]
+    explain_near_misses: bool,
+
+    /// Also match literal entries anywhere within an identifier, so that banning
+    /// `Password` catches `userPassword`
+    #[arg(long)]
+    substring: bool,
+
+    /// Ignore the case of the first character when comparing identifiers against
+    /// entries, so that banning `foo` also catches the constructor `Foo`
+    ///
+    /// Only the first character is folded: `fooBar` still doesn't match `FOOBAR`.
+    /// Exceptions are folded the same way.
+    #[arg(long)]
+    fold_leading_case: bool,
+
+    /// Ignore case entirely when comparing identifiers against entries and exceptions,
+    /// so that banning `foo` also catches `FOO` and `fOo`
+    #[arg(long)]
+    ignore_case: bool,
+
+    /// Match qualified names like `Unsafe.Coerce.unsafeCoerce` against entries
+    /// naming them in full, and report the whole qualified name
+    ///
+    /// Qualified operators are matched the same way, so an entry `Map.!` catches
+    /// `m Map.! k`, `(Map.!)` and the section `(Map.! k)`, but not an unqualified `!`.
+    #[arg(long)]
+    qualified: bool,
+
+    /// Treat entries outside of `[query]` sections as module names, and report every
+    /// import of them, qualified or not
+    ///
+    /// Module entries only match whole module names, so `Database.*` bans importing
+    /// any module beneath `Database` while `Data.Map` doesn't ban `Data.Map.Strict`.
+    #[arg(long)]
+    ban_modules: bool,
+
+    /// Treat apostrophes as word characters when detecting word boundaries
+    ///
+    /// Identifiers are always compared whole first, so a banned `foo'` only ever
+    /// matches `foo'`. When this is false, `foo'` and `x''` also split into the
+    /// words `foo` and `x`, so banning `foo` catches its primed variants too.
+    #[arg(long, action = ArgAction::Set, default_value_t = true)]
+    prime_is_word_char: bool,
+
+    /// Report the paths of files inside a git repository relative to its root
+    ///
+    /// This keeps reports the same from whichever directory of the repository the scan
+    /// is run. Outside of a repository, or when this is false, paths are reported as
+    /// given. Only reported paths change; `in` annotations and exemptions still match
+    /// the paths as given.
+    #[arg(long, action = ArgAction::Set, default_value_t = true)]
+    relativize_to_git_root: bool,
+
+    /// Write locations the way editors on the current platform expect them
+    ///
+    /// Locations are normally `(path:line:column)` with forward slashes kept as given
+    /// and a 0-based byte column. With this flag, columns are 1-based and paths use the
+    /// platform's separator, so on Windows `src/A.hs` at the first byte of line 3 is
+    /// written `(src\A.hs:3:1)`; elsewhere only the column changes.
+    #[arg(long)]
+    relative_line_anchors: bool,
+
+    /// Report columns with tabs expanded to this many columns, as an editor shows them,
+    /// rather than as byte offsets
+    #[arg(long, value_name = "N")]
+    tab_width: Option<NonZeroUsize>,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+enum LocalTaboo {
+    /// Add the entries of every `.taboo` file above a file to the main list
+    Merge,
+    /// Use only the nearest `.taboo` file above a file, ignoring the main list
+    Override,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+enum Report {
+    /// Each banned word with its number of violations and some example locations
+    GroupedByWord,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+enum ColorChoice {
+    /// Color output to a terminal, deferring to NO_COLOR, CLICOLOR and CLICOLOR_FORCE
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    /// Whether output to `stderr`, or to a file with `to_file`, should be colored.
+    fn enabled(self, to_file: bool) -> bool {
+        let set = |name| std::env::var_os(name).is_some_and(|value| !value.is_empty());
+        let is = |name, expected: &str| std::env::var_os(name).is_some_and(|v| v == expected);
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto if set("CLICOLOR_FORCE") && !is("CLICOLOR_FORCE", "0") => true,
+            ColorChoice::Auto if set("NO_COLOR") || is("CLICOLOR", "0") => false,
+            // violations are highlighted on stderr, which may be a terminal when stdout isn't
+            ColorChoice::Auto => !to_file && std::io::stderr().is_terminal(),
+        }
+    }
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+enum OccurrenceScope {
+    /// Count every violation of a word in the scan
+    Run,
+    /// Count the violations of a word in each file separately
+    File,
+}
+
+impl Args {
+    /// The `--format` violations are written in, unless they're reported for people.
+    fn structured_format(&self) -> Option<Format> {
+        self.format.filter(|format| *format != Format::Human)
+    }
+
+    /// The exemptions file, which is only read when given or when --review writes to it.
+    fn exemptions_file(&self) -> Option<&Path> {
+        match &self.exemptions {
+            Some(path) => Some(path),
+            None => self.review.then_some(Path::new(".taboo-exemptions")),
+        }
+    }
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+enum Format {
+    /// The report for people, on stderr
+    Human,
+    /// A single JSON object, written once the scan finishes
+    Json,
+    /// One JSON object per line, written as violations are found
+    Ndjson,
+    /// reviewdog's diagnostic JSON, for reviewdog to comment on violations in review
+    Rdjson,
+    /// A SARIF 2.1.0 log, for uploading to GitHub code scanning
+    Sarif,
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum Command {
+    /// Validate a banned words list without scanning any files
+    CheckTaboo(CheckTabooArgs),
+}
+
+#[derive(clap::Args, Debug)]
+struct CheckTabooArgs {
+    /// Location of banned words list
+    taboo: String,
+
+    /// Exit with an error if any problems are found in the list
+    #[arg(long)]
+    strict_taboo: bool,
 }
 
 fn main() -> ExitCode {
     let args = Args::parse_from(wild::args());
 
-    match find_banned_words(&args) {
+    let result = match &args.command {
+        Some(Command::CheckTaboo(check_args)) => check_taboo(check_args),
+        None => find_banned_words(&args),
+    };
+
+    match result {
         Ok(false) => ExitCode::SUCCESS,
-        Ok(true) => ExitCode::FAILURE,
+        Ok(true) => ExitCode::from(EXIT_VIOLATIONS),
         Err(e) => {
             eprintln!("{}", e);
-            ExitCode::FAILURE
+            exit_code_for(&e)
         }
     }
 }
 
+/// Reports problems with a banned words list, returning whether any were found.
+fn check_taboo(args: &CheckTabooArgs) -> anyhow::Result<bool> {
+    let lint = lint_taboo_file(Path::new(&args.taboo))?;
+    for problem in &lint.problems {
+        eprintln!("warning: {problem}");
+    }
+    if lint.blank_lines > 0 {
+        eprintln!("note: {} blank line(s) dropped", lint.blank_lines);
+    }
+    for counts in &lint.counts {
+        eprintln!("{counts}");
+    }
+
+    Ok(args.strict_taboo && !lint.problems.is_empty())
+}
+
 fn find_banned_words(args: &Args) -> anyhow::Result<bool> {
-    let taboo_file = File::open(&args.taboo)
-        .with_context(|| format!("Error opening taboo file {}", args.taboo))?;
+    let taboo = args
+        .taboo
+        .as_deref()
+        .expect("taboo is required without a subcommand");
+    if let Some(command) = &args.exec {
+        check_command(command)?;
+    }
+    let taboo_set = load_taboo_file(Path::new(taboo))?;
 
-    let banned_words_set: HashSet<String> = banned_words_from(&taboo_file);
+    let read_stdin = args.files.iter().any(|file| file == "-")
+        || (args.stdin_filename.is_some() && args.files.is_empty());
+    let mut source_dirs = Vec::new();
+    let mut test_paths: Vec<PathBuf> = if !args.files.is_empty() {
+        let mut paths = Vec::new();
+        for file in args.files.iter().filter(|file| *file != "-") {
+            if Path::new(file).is_dir() {
+                paths.extend(walk_files(Path::new(file), &args.exclude)?);
+            } else if is_glob(file) {
+                paths.extend(glob_files(file, &args.exclude)?);
+            } else if Path::new(file).exists() {
+                paths.push(PathBuf::from(file));
+            } else {
+                return Err(CliError::MissingPath {
+                    path: PathBuf::from(file),
+                }
+                .into());
+            }
+        }
+        paths
+    } else if args.archive.is_none()
+        && args.source.is_none()
+        && args.files_from.is_none()
+        && !read_stdin
+    {
+        if args.no_default_dir {
+            return Err(CliError::NoFiles.into());
+        }
 
-    let test_paths: Vec<PathBuf> = if args.files.is_empty() {
-        fs::read_dir("src")
-            .context("Failed to read src/ directory")?
-            .map(|di| di.unwrap().path())
-            .collect()
+        source_dirs = default_source_dirs(args)?;
+        let mut paths = Vec::new();
+        for source_dir in &source_dirs {
+            paths.extend(walk_files(source_dir, &args.exclude)?);
+        }
+        paths.sort();
+        paths.dedup();
+        paths
+    } else {
+        Vec::new()
+    };
+
+    if let Some(manifest) = &args.files_from {
+        test_paths.extend(manifest_files(manifest)?);
+    }
+
+    let mut sources: Vec<Source> = test_paths.into_iter().map(Source::File).collect();
+    if read_stdin {
+        let path = args
+            .stdin_filename
+            .clone()
+            .unwrap_or_else(|| PathBuf::from("<stdin>"));
+        let mut bytes = Vec::new();
+        // a dump only names the source, which shouldn't wait on whatever is piped in
+        if !args.config_dump {
+            std::io::stdin()
+                .read_to_end(&mut bytes)
+                .map_err(|source| CliError::SourceFile {
+                    path: path.clone(),
+                    source,
+                })?;
+        }
+        sources.push(Source::Stdin(path, bytes));
+    }
+    if let Some(code) = &args.source {
+        sources.push(Source::Memory(
+            PathBuf::from("<inline>"),
+            code.clone().into_bytes(),
+        ));
+    }
+    if let Some(archive) = &args.archive {
+        let entries = haskell_entries(archive)?;
+        sources.extend(
+            entries
+                .into_iter()
+                .map(|(name, bytes)| Source::Memory(name, bytes)),
+        );
+    }
+
+    let color = if args.force_color {
+        ColorChoice::Always
     } else {
-        args.files.iter().map(|s| PathBuf::from(s)).collect()
+        args.color
     };
+    let color_enabled = color.enabled(args.output.is_some());
+    if args.config_dump {
+        let effective =
+            EffectiveTabooSets::resolve(Path::new(taboo), &taboo_set, &sources, args.local_taboo)?;
+        let config = ResolvedConfig {
+            args,
+            color_enabled,
+            source_dirs,
+            sources: sources
+                .iter()
+                .zip(&effective.for_source)
+                .map(|(source, set)| ResolvedSource {
+                    path: source.path(),
+                    taboo_files: &effective.taboo_files[*set],
+                })
+                .collect(),
+        };
+        let mut stdout = std::io::stdout().lock();
+        match writeln!(stdout, "{}", serde_json::to_string_pretty(&config)?) {
+            // `--config-dump | head` has seen all it wants
+            Err(e) if e.kind() == std::io::ErrorKind::BrokenPipe => {}
+            written => written?,
+        }
+        return Ok(false);
+    }
+
+    let mut output = match &args.output {
+        Some(path) => Output::to_file(path)?,
+        None => Output::standard(),
+    };
+    colored::control::set_override(color_enabled);
+
+    let started = Instant::now();
+
+    if args.structured_format() == Some(Format::Ndjson) {
+        json::write_header(&mut output.stdout)?;
+    }
+    let mut summary =
+        check_paths_for_banned_words(Path::new(taboo), &taboo_set, &sources, args, &mut output)?;
+
+    if args.annotate {
+        require_clean_tree(
+            "annotate",
+            summary.annotations.iter().map(|(path, _)| path.as_path()),
+        )?;
+        let mut annotated = 0;
+        for (path, line_ends) in &summary.annotations {
+            annotated += annotate_file(path, line_ends)?;
+        }
+        writeln!(
+            output.stderr,
+            "annotated {} line(s) in {} file(s)",
+            annotated,
+            summary.annotations.len()
+        )?;
+        return Ok(false);
+    }
+
+    if let Some(path) = &args.write_baseline {
+        let totals = Totals {
+            errors: summary.errors,
+            warnings: summary.warnings,
+            files: summary.files,
+        };
+        let baseline_error = |source| CliError::Baseline {
+            path: path.clone(),
+            source,
+        };
+        let mut file = File::create(path).map_err(baseline_error)?;
+        json::write_report(&mut file, &summary.findings, &totals).map_err(baseline_error)?;
+        writeln!(
+            output.stderr,
+            "wrote {} violation(s) to baseline {}",
+            summary.findings.len(),
+            path.display()
+        )?;
+        return Ok(false);
+    }
+
+    if args.fix {
+        if !args.dry_run {
+            require_clean_tree("fix", summary.fixes.iter().map(|(path, _)| path.as_path()))?;
+        }
+        let mut fixed = 0;
+        for (path, edits) in &summary.fixes {
+            let diff = args.dry_run.then_some(&mut output.stdout);
+            fixed += fix_file(path, edits, diff)?;
+        }
+        writeln!(
+            output.stderr,
+            "{} {} occurrence(s) in {} file(s)",
+            if args.dry_run { "would fix" } else { "fixed" },
+            fixed,
+            summary.fixes.len()
+        )?;
+    }
+
+    if args.review {
+        let exemptions = args
+            .exemptions_file()
+            .expect("--review has an exemptions file");
+        let outcome = review(&summary.review_items, exemptions)?;
+        for index in &outcome.allowed {
+            summary.uncount(summary.review_items[*index].severity);
+        }
+        writeln!(
+            output.stderr,
+            "allowed {}, skipped {}, {} not reviewed",
+            outcome.allowed.len(),
+            outcome.skipped,
+            summary.review_items.len() - outcome.allowed.len() - outcome.skipped
+        )?;
+    }
+
+    if args.bench {
+        let seconds = started.elapsed().as_secs_f64();
+        let megabytes = summary.bytes as f64 / 1_000_000.0;
+        writeln!(
+            output.stderr,
+            "scanned {} files ({:.2} MB) in {:.3}s: {:.1} files/s, {:.2} MB/s",
+            summary.files,
+            megabytes,
+            seconds,
+            summary.files as f64 / seconds,
+            megabytes / seconds
+        )?;
+        return Ok(false);
+    }
+
+    let totals = Totals {
+        errors: summary.errors,
+        warnings: summary.warnings,
+        files: summary.files,
+    };
+    match args.format {
+        Some(Format::Json) => json::write_report(&mut output.stdout, &summary.findings, &totals)?,
+        Some(Format::Ndjson) => json::write_totals(&mut output.stdout, &totals)?,
+        Some(Format::Rdjson) => json::write_rdjson(&mut output.stdout, &summary.diagnostics)?,
+        Some(Format::Sarif) => write_sarif(&mut output.stdout, &mut summary.sarif_results)?,
+        _ if args.count_only => writeln!(output.stdout, "{}", summary.violations())?,
+        _ if summary.violations() > 0 => writeln!(
+            output.stderr,
+            "{} error(s), {} warning(s)",
+            summary.errors, summary.warnings
+        )?,
+        _ => {}
+    }
 
-    check_paths_for_banned_words(&banned_words_set, &test_paths)
+    if args.show_suppressed {
+        report_suppressed(&mut output, &summary.suppressed)?;
+    }
+
+    if !summary.gone_from_baseline.is_empty() {
+        writeln!(
+            output.stderr,
+            "{} violation(s) in the baseline are gone, and writing it again drops them:",
+            summary.gone_from_baseline.len()
+        )?;
+        for gone in &summary.gone_from_baseline {
+            writeln!(output.stderr, "  {}", gone)?;
+        }
+    }
+
+    if args.report == Some(Report::GroupedByWord) {
+        report_grouped_by_word(&mut output, &summary)?;
+    }
+
+    if args.report_unused {
+        report_unused(&mut output, &taboo_set, &summary)?;
+    }
+
+    if let Some(command) = &args.exec {
+        let failed = exec_for_each(command, &summary.offending_files, &mut output.stderr)?;
+        if failed > 0 {
+            return Err(CliError::ExecFailed {
+                failed,
+                total: summary.offending_files.len(),
+            }
+            .into());
+        }
+    }
+
+    Ok(summary.exceeds_budgets(args) && !args.no_fail)
 }
 
-fn banned_words_from(file: &File) -> HashSet<String> {
-    let buf_read = BufReader::new(file);
-    buf_read
-        .lines()
-        .map(|l| l.unwrap().trim().to_string())
-        .filter(|l| !l.is_empty())
-        .collect()
+/// Prints how many violations were suppressed, broken down by the ways that were used.
+fn report_suppressed(output: &mut Output, suppressed: &Suppressed) -> anyhow::Result<()> {
+    let reasons: Vec<String> = [
+        (suppressed.markers, "`-- taboo:allow` markers"),
+        (suppressed.comments, "`taboo: ignore` comments"),
+        (suppressed.allowances, "[allow] entries"),
+        (suppressed.exemptions, "exemptions"),
+        (suppressed.baseline, "the baseline"),
+    ]
+    .into_iter()
+    .filter(|(count, _)| *count > 0)
+    .map(|(count, reason)| format!("{} by {}", count, reason))
+    .collect();
+
+    if reasons.is_empty() {
+        writeln!(output.stderr, "suppressed 0 violation(s)")?;
+    } else {
+        writeln!(
+            output.stderr,
+            "suppressed {} violation(s): {}",
+            suppressed.total(),
+            reasons.join(", ")
+        )?;
+    }
+    Ok(())
 }
 
-fn check_paths_for_banned_words(
-    banned_words_set: &HashSet<String>,
-    paths: &Vec<PathBuf>,
-) -> anyhow::Result<bool> {
-    let query = Query::new(
-        &tree_sitter_haskell::LANGUAGE.into(),
-        "(variable) @variable-name",
-    )
-    .expect("Error constructing name query");
+/// Lists each word that violations matched, most often matched first, with examples of
+/// where it was found.
+fn report_grouped_by_word(output: &mut Output, summary: &ScanSummary) -> anyhow::Result<()> {
+    let mut words: Vec<(&String, &WordHits)> = summary.word_hits.iter().collect();
+    words.sort_by(|(a_word, a), (b_word, b)| b.count.cmp(&a.count).then(a_word.cmp(b_word)));
+
+    for (word, hits) in words {
+        writeln!(output.stderr, "{}: {} violation(s)", word, hits.count)?;
+        for example in &hits.examples {
+            writeln!(output.stderr, "  ({})", example)?;
+        }
+        if hits.count > hits.examples.len() {
+            writeln!(
+                output.stderr,
+                "  ... and {} more",
+                hits.count - hits.examples.len()
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Lists the entries of `taboo_set` that no reported violation matched.
+///
+/// An entry that only ever matched identifiers permitted by an exception counts as
+/// unused, since removing it would change nothing.
+fn report_unused(
+    output: &mut Output,
+    taboo_set: &TabooSet,
+    summary: &ScanSummary,
+) -> anyhow::Result<()> {
+    let mut unused: Vec<&str> = taboo_set
+        .bans()
+        .chain(taboo_set.sections.iter().flat_map(|s| s.taboo_set.bans()))
+        .map(|ban| ban.word.as_str())
+        .filter(|word| !summary.used_entries.contains(*word))
+        .collect();
+    unused.sort_unstable();
+    unused.dedup();
 
-    let mut parser = tree_sitter::Parser::new();
+    if unused.is_empty() {
+        writeln!(output.stderr, "every banned entry was used")?;
+    } else {
+        writeln!(output.stderr, "unused banned entries:")?;
+        for word in unused {
+            writeln!(output.stderr, "  {}", word)?;
+        }
+    }
+    Ok(())
+}
 
-    parser
-        .set_language(&tree_sitter_haskell::LANGUAGE.into())
-        .expect("Error loading Haskell grammar");
+/// Totals for a whole scan.
+#[derive(Debug, Default)]
+struct ScanSummary {
+    errors: usize,
+    warnings: usize,
+    /// The violations of each banned entry, or of each alias group as a whole, kept only
+    /// for `--report`.
+    word_hits: HashMap<String, WordHits>,
+    /// The entries with violations, kept only for `--report-unused`.
+    used_entries: HashSet<String>,
+    /// Every violation, kept only for `--review`.
+    review_items: Vec<ReviewItem>,
+    /// Every violation, kept only for `--format json`.
+    findings: Vec<json::Finding>,
+    /// Every violation, kept only for `--format rdjson`.
+    diagnostics: Vec<Diagnostic>,
+    /// Every violation, kept only for `--format sarif`.
+    sarif_results: Vec<SarifResult>,
+    /// The rewrites of the violations in each file that have a replacement, kept only
+    /// for `--fix`.
+    fixes: Vec<(PathBuf, Vec<Edit>)>,
+    /// The end of each line with a violation in each file, kept only for `--annotate`.
+    annotations: Vec<(PathBuf, Vec<usize>)>,
+    suppressed: Suppressed,
+    /// The `path:line: word` of each violation recorded in the `--baseline` that is gone.
+    gone_from_baseline: Vec<String>,
+    /// Files on disk with a violation, kept only for `--exec`.
+    offending_files: Vec<PathBuf>,
+    /// Files that were read and parsed.
+    files: usize,
+    bytes: usize,
+}
 
-    let mut seen_banned_word = false;
+/// How many violations were left unreported by each way of suppressing them.
+#[derive(Debug, Default)]
+struct Suppressed {
+    exemptions: usize,
+    markers: usize,
+    comments: usize,
+    allowances: usize,
+    baseline: usize,
+}
 
-    for path in paths {
-        let file = File::open(&path)?;
-        // SAFETY: we assume that source files do not change during the execution of this program
-        let mmap = unsafe { Mmap::map(&file)? };
-        let mmap_slice: &[u8] = &mmap;
+impl Suppressed {
+    fn total(&self) -> usize {
+        self.exemptions + self.markers + self.comments + self.allowances + self.baseline
+    }
+}
 
-        // skip checking the file if parsing as Haskell fails
-        let Some(tree) = parser.parse(mmap_slice, None) else {
-            continue;
+#[derive(Debug, Default)]
+struct WordHits {
+    count: usize,
+    /// The locations of the first `--examples` violations.
+    examples: Vec<String>,
+}
+
+impl ScanSummary {
+    fn violations(&self) -> usize {
+        self.errors + self.warnings
+    }
+
+    fn count(&mut self, severity: Severity) {
+        match severity {
+            Severity::Error => self.errors += 1,
+            Severity::Warning => self.warnings += 1,
+        }
+    }
+
+    fn uncount(&mut self, severity: Severity) {
+        match severity {
+            Severity::Error => self.errors -= 1,
+            Severity::Warning => self.warnings -= 1,
+        }
+    }
+
+    /// Whether either severity went over its `--max-errors` or `--max-warnings` budget.
+    fn exceeds_budgets(&self, args: &Args) -> bool {
+        self.errors > args.max_errors
+            || args
+                .max_warnings
+                .is_some_and(|max_warnings| self.warnings > max_warnings)
+    }
+}
+
+/// The directories to scan when no files are given.
+fn default_source_dirs(args: &Args) -> Result<Vec<PathBuf>, CliError> {
+    if let Some(default_dir) = &args.default_dir {
+        return Ok(vec![default_dir.clone()]);
+    }
+
+    // hpack generates the .cabal file, so package.yaml is the source of truth when present
+    for discover in [hpack_source_dirs, cabal_source_dirs] {
+        match discover(Path::new("."))? {
+            Some(source_dirs) if !source_dirs.is_empty() => return Ok(source_dirs),
+            _ => {}
+        }
+    }
+
+    Ok(vec![PathBuf::from("src")])
+}
+
+/// A file to scan, either read from disk or already in memory.
+enum Source {
+    File(PathBuf),
+    /// Contents from somewhere other than the file system, like an archive entry,
+    /// reported under the given path.
+    Memory(PathBuf, Vec<u8>),
+    /// Contents piped in on stdin, standing in for the file at the given path, if it
+    /// was named with `--stdin-filename`.
+    Stdin(PathBuf, Vec<u8>),
+}
+
+impl Source {
+    /// The path the source is reported under.
+    fn path(&self) -> &Path {
+        match self {
+            Source::File(path) | Source::Memory(path, _) | Source::Stdin(path, _) => path,
+        }
+    }
+}
+
+/// What `--config-dump` prints: the options as given, along with what they resolve to.
+#[derive(Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct ResolvedConfig<'a> {
+    #[serde(flatten)]
+    args: &'a Args,
+    /// Whether violations are highlighted, once `--force-color` and the environment
+    /// are taken into account.
+    color_enabled: bool,
+    /// The directories scanned for want of file arguments, from `--default-dir`, a
+    /// `.cabal` file or `package.yaml`.
+    source_dirs: Vec<PathBuf>,
+    sources: Vec<ResolvedSource<'a>>,
+}
+
+/// A source to scan, along with the taboo files whose entries apply to it.
+#[derive(Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct ResolvedSource<'a> {
+    path: &'a Path,
+    taboo_files: &'a [PathBuf],
+}
+
+/// Where a scan writes its report.
+///
+/// Text meant for people all goes to `stderr`, leaving `stdout` for what other programs
+/// read: the `--format` payload, `--dry-run` diffs and the `--count-only` total.
+struct Output {
+    stdout: Box<dyn Write>,
+    stderr: Box<dyn Write>,
+}
+
+impl Output {
+    fn standard() -> Self {
+        Output {
+            stdout: Box::new(std::io::stdout()),
+            stderr: Box::new(std::io::stderr()),
+        }
+    }
+
+    /// Sends both streams to `path`.
+    fn to_file(path: &PathBuf) -> Result<Self, CliError> {
+        let output_error = |source| CliError::OutputFile {
+            path: path.clone(),
+            source,
         };
+        let file = File::create(path).map_err(output_error)?;
 
-        let mut query_cursor = QueryCursor::new();
-        let mut names = query_cursor.matches(&query, tree.root_node(), mmap_slice);
+        Ok(Output {
+            stdout: Box::new(file.try_clone().map_err(output_error)?),
+            stderr: Box::new(file),
+        })
+    }
+}
+
+/// The entries in effect for each source once `--local-taboo` files are applied.
+struct EffectiveTabooSets {
+    sets: Vec<TabooSet>,
+    /// The taboo files each of `sets` was loaded from, outermost first.
+    taboo_files: Vec<Vec<PathBuf>>,
+    /// The index into `sets` of each source's entries.
+    for_source: Vec<usize>,
+}
+
+impl EffectiveTabooSets {
+    fn resolve(
+        taboo: &Path,
+        taboo_set: &TabooSet,
+        sources: &[Source],
+        mode: Option<LocalTaboo>,
+    ) -> anyhow::Result<Self> {
+        let mut resolved = EffectiveTabooSets {
+            sets: vec![taboo_set.clone()],
+            taboo_files: vec![vec![taboo.to_path_buf()]],
+            for_source: vec![0; sources.len()],
+        };
+        let Some(mode) = mode else {
+            return Ok(resolved);
+        };
 
-        while let Some(name) = names.next() {
-            for capture in name.captures {
-                let text = capture.node.utf8_text(mmap_slice)?;
+        // sources in the same directory, or beneath the same `.taboo` files, share a set
+        let mut for_dir: HashMap<PathBuf, usize> = HashMap::new();
+        let mut for_files: HashMap<Vec<PathBuf>, usize> = HashMap::new();
+        for (source, set) in sources.iter().zip(&mut resolved.for_source) {
+            // archive entries have no directories to look in
+            let (Source::File(path) | Source::Stdin(path, _)) = source else {
+                continue;
+            };
+            let dir = path.parent().unwrap_or(Path::new("")).to_path_buf();
+            if let Some(index) = for_dir.get(&dir) {
+                *set = *index;
+                continue;
+            }
+
+            let mut taboo_files = local_taboo_files(&dir);
+            if mode == LocalTaboo::Override {
+                taboo_files.truncate(1);
+            }
 
-                // if a variable isn't a banned word we don't need to process it
-                if !(banned_words_set.contains(text)) {
-                    continue;
+            let index = match for_files.get(&taboo_files) {
+                Some(index) => *index,
+                None if taboo_files.is_empty() => 0,
+                None => {
+                    let (mut effective, mut loaded_from) = match mode {
+                        LocalTaboo::Merge => (taboo_set.clone(), vec![taboo.to_path_buf()]),
+                        LocalTaboo::Override => (TabooSet::default(), Vec::new()),
+                    };
+                    // outer files first, so that the order entries apply in matches the tree
+                    for taboo_file in taboo_files.iter().rev() {
+                        effective.extend(&load_taboo_file(taboo_file)?);
+                        loaded_from.push(taboo_file.clone());
+                    }
+
+                    resolved.sets.push(effective);
+                    resolved.taboo_files.push(loaded_from);
+                    let index = resolved.sets.len() - 1;
+                    for_files.insert(taboo_files, index);
+                    index
                 }
+            };
+            for_dir.insert(dir, index);
+            *set = index;
+        }
 
-                if !seen_banned_word {
-                    println!("ERROR: Banned identifiers found");
-                    println!("Found the following issues:");
+        Ok(resolved)
+    }
+}
+
+/// Returns the `.taboo` files in `dir` and its ancestors, nearest first.
+fn local_taboo_files(dir: &Path) -> Vec<PathBuf> {
+    dir.ancestors()
+        .map(|ancestor| ancestor.join(".taboo"))
+        .filter(|taboo_file| taboo_file.is_file())
+        .collect()
+}
+
+/// Lines at the start of a file checked for a generated-file marker.
+const GENERATED_MARKER_LINES: usize = 10;
+
+/// Whether one of the first lines of `source` matches `marker`.
+fn is_generated(source: &[u8], marker: &Regex) -> bool {
+    source
+        .split(|b| *b == b'\n')
+        .take(GENERATED_MARKER_LINES)
+        .any(|line| marker.is_match(&String::from_utf8_lossy(line)))
+}
+
+fn serialize_regex<S: serde::Serializer>(regex: &Regex, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(regex.as_str())
+}
+
+fn map_source(path: &Path) -> Result<Mmap, CliError> {
+    File::open(path)
+        .and_then(|file| {
+            // SAFETY: we assume that source files do not change during the execution of this program
+            unsafe { Mmap::map(&file) }
+        })
+        .map_err(|source| CliError::SourceFile {
+            path: path.to_path_buf(),
+            source,
+        })
+}
 
-                    seen_banned_word = true;
+/// A source once it has been read and its banned words found, ready to be reported on.
+enum Prepared {
+    Found {
+        /// The contents of a file read from disk.
+        mmap: Option<Mmap>,
+        found: Found,
+        /// The cache key of a file that wasn't in the cache, to add it under.
+        uncached: Option<String>,
+    },
+    Unreadable(CliError),
+    /// A generated file skipped by `--skip-generated`, at the given path.
+    Generated(PathBuf),
+    /// Finding banned words failed, e.g. because the source couldn't be parsed.
+    Failed(TabooError),
+}
+
+/// Reads `source` and finds its banned words with `checker`, unless the source is a
+/// file that `cache`, along with the key of its entries, already has.
+fn prepare(
+    source: &Source,
+    checker: &Checker,
+    cache: Option<(&Cache, &str)>,
+    args: &Args,
+) -> Prepared {
+    let mmap = match source {
+        Source::File(path) => match map_source(path) {
+            Ok(mmap) => Some(mmap),
+            Err(e) => return Prepared::Unreadable(e),
+        },
+        Source::Memory(..) | Source::Stdin(..) => None,
+    };
+    let (path, bytes) = source_contents(source, mmap.as_ref());
+    if args.skip_generated && is_generated(bytes, &args.generated_marker) {
+        return Prepared::Generated(path.to_path_buf());
+    }
+
+    let uncached = match (cache, source) {
+        (Some((cache, set_key)), Source::File(_)) => {
+            let key = cache::key(set_key, bytes);
+            if let Some(found) = cache.get(path, &key) {
+                let found = found.clone();
+                return Prepared::Found {
+                    mmap,
+                    found,
+                    uncached: None,
+                };
+            }
+            Some(key)
+        }
+        _ => None,
+    };
+    match checker.find(path, bytes) {
+        Ok(found) => Prepared::Found {
+            mmap,
+            found,
+            uncached,
+        },
+        Err(e) => Prepared::Failed(e),
+    }
+}
+
+/// The path `source` is reported under and its contents, which for a file are `mmap`.
+fn source_contents<'a>(source: &'a Source, mmap: Option<&'a Mmap>) -> (&'a Path, &'a [u8]) {
+    match (source, mmap) {
+        (Source::File(path), Some(mmap)) => (path, mmap),
+        (Source::File(_), None) => unreachable!("files are mapped before they're scanned"),
+        (Source::Memory(path, bytes) | Source::Stdin(path, bytes), _) => (path, bytes),
+    }
+}
+
+fn check_paths_for_banned_words(
+    taboo: &Path,
+    taboo_set: &TabooSet,
+    sources: &[Source],
+    args: &Args,
+    output: &mut Output,
+) -> anyhow::Result<ScanSummary> {
+    let effective = EffectiveTabooSets::resolve(taboo, taboo_set, sources, args.local_taboo)?;
+    let scan_options = ScanOptions {
+        matching: MatchOptions {
+            prime_is_word_char: args.prime_is_word_char,
+            substring: args.substring,
+            fold_leading_case: args.fold_leading_case,
+            ignore_case: args.ignore_case,
+        },
+        only: args.only,
+        include_pragmas: args.include_pragmas,
+        qualified: args.qualified,
+        ignore_in: args.ignore_in.clone(),
+        timeout: args.timeout_per_file.map(Duration::from_millis),
+    };
+    let check_options = CheckOptions {
+        scan: scan_options,
+        kinds: args.kinds.clone(),
+        ban_modules: args.ban_modules,
+    };
+    let checkers = effective
+        .sets
+        .iter()
+        .map(|set| Checker::new(set, &check_options))
+        .collect::<Result<Vec<_>, TabooError>>()?;
+
+    let mut cache = args.cache.as_deref().map(Cache::load);
+    let set_keys = if cache.is_some() {
+        let settings = format!("{check_options:?}");
+        effective
+            .taboo_files
+            .iter()
+            .map(|taboo_files| cache::set_key(&settings, taboo_files))
+            .collect::<Result<Vec<_>, CliError>>()?
+    } else {
+        Vec::new()
+    };
+
+    // sources are parsed in parallel, but reported on one at a time in the order given
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(args.jobs.map_or(0, NonZeroUsize::get))
+        .build()?;
+    let mut prepared: Vec<Prepared> = pool.install(|| {
+        sources
+            .par_iter()
+            .zip(&effective.for_source)
+            .map(|(source, set)| {
+                let cache = cache.as_ref().map(|cache| (cache, set_keys[*set].as_str()));
+                prepare(source, &checkers[*set], cache, args)
+            })
+            .collect()
+    });
+
+    if let Some(cache) = &mut cache {
+        for (source, prepared) in sources.iter().zip(&mut prepared) {
+            if let (
+                Source::File(path),
+                Prepared::Found {
+                    found, uncached, ..
+                },
+            ) = (source, prepared)
+                && let Some(key) = uncached.take()
+            {
+                cache.insert(path, key, found.clone());
+            }
+        }
+    }
+
+    let mut summary = ScanSummary::default();
+    let mut skipped_files = 0;
+    // benchmarking still finds every violation, but doesn't spend time printing them
+    let quiet = args.count_only
+        || args.bench
+        || args.review
+        || args.annotate
+        || args.report.is_some()
+        || args.structured_format().is_some()
+        || args.write_baseline.is_some();
+    // a baseline is written as a JSON report
+    let finding_format = args
+        .structured_format()
+        .or(args.write_baseline.as_ref().map(|_| Format::Json));
+    let exemptions = match args.exemptions_file() {
+        Some(path) => Exemptions::load(path)?,
+        None => Exemptions::default(),
+    };
+    let report_root = git_root().filter(|_| args.relativize_to_git_root);
+    let mut previous = args
+        .compare_to
+        .as_deref()
+        .map(PreviousReport::load)
+        .transpose()?;
+    let mut baseline = args
+        .baseline
+        .as_deref()
+        .map(PreviousReport::load)
+        .transpose()?;
+    // the files a baseline's violations could have been found in this time
+    let mut checked_against_baseline = HashSet::new();
+    let changed_lines = (args.changed_since.is_some() || args.staged)
+        .then(|| ChangedLines::load(args.changed_since.as_deref(), args.staged))
+        .transpose()?;
+
+    let thresholds =
+        args.min_occurrences.is_some() || effective.sets.iter().any(TabooSet::has_min_occurrences);
+    // a word's count across the run is only known once every file has been scanned
+    let run_counts = if thresholds && args.occurrence_scope == OccurrenceScope::Run {
+        count_violations(
+            &checkers,
+            sources,
+            &prepared,
+            &effective.for_source,
+            &exemptions,
+        )?
+    } else {
+        HashMap::new()
+    };
+
+    for ((source, set), prepared) in sources.iter().zip(&effective.for_source).zip(prepared) {
+        let (mmap, found) = match prepared {
+            Prepared::Found { mmap, found, .. } => (mmap, found),
+            // a file can vanish or be replaced between collecting paths and opening it,
+            // which shouldn't fail the whole scan
+            Prepared::Unreadable(e) => {
+                writeln!(output.stderr, "warning: skipping {}", e)?;
+                skipped_files += 1;
+                continue;
+            }
+            Prepared::Generated(path) => {
+                if !quiet {
+                    writeln!(
+                        output.stderr,
+                        "note: skipping generated file {}",
+                        path.display()
+                    )?;
                 }
+                continue;
+            }
+            Prepared::Failed(e @ TabooError::ParseTimeout { .. }) => {
+                writeln!(output.stderr, "warning: skipping {}", e)?;
+                continue;
+            }
+            // skip checking the file if parsing as Haskell fails
+            Prepared::Failed(TabooError::Parse { .. }) => continue,
+            Prepared::Failed(e) => return Err(e.into()),
+        };
+        let (path, mmap_slice) = source_contents(source, mmap.as_ref());
+        let mut checked = checkers[*set].check_found(path, mmap_slice, &found)?;
+        retain_reportable(&mut checked, &exemptions, &mut summary.suppressed);
+        summary.files += 1;
+        summary.bytes += mmap_slice.len();
+
+        // archive entries and inline source aren't files in the repository
+        let shown = match (&report_root, source) {
+            (Some(root), Source::File(_) | Source::Stdin(..)) => relative_to(path, root),
+            _ => Cow::Borrowed(path),
+        };
 
-                let start_byte = capture.node.start_byte();
-                let end_byte = capture.node.end_byte();
-                let slice_before = &mmap_slice[0..start_byte];
-                let line_first_char = slice_before
+        let Checked {
+            findings: mut violations,
+            mut near_misses,
+            ..
+        } = checked;
+        if thresholds {
+            let mut file_counts = HashMap::new();
+            let counts = match args.occurrence_scope {
+                OccurrenceScope::Run => &run_counts,
+                OccurrenceScope::File => {
+                    violations_per_word(&violations, &mut file_counts);
+                    &file_counts
+                }
+            };
+            violations.retain(|v| {
+                let min = v.min_occurrences.or(args.min_occurrences);
+                min.is_none_or(|min| counts.get(&v.canonical).is_some_and(|n| *n >= min))
+            });
+        }
+        if let Some(previous) = &mut previous {
+            let shown = shown.display().to_string();
+            violations.retain(|v| {
+                let fingerprint = violation_fingerprint(&shown, mmap_slice, v);
+                !previous.take(&fingerprint, &shown, v.line, &v.word)
+            });
+        }
+        if let Some(baseline) = &mut baseline {
+            let shown = shown.display().to_string();
+            violations.retain(|v| {
+                let fingerprint = violation_fingerprint(&shown, mmap_slice, v);
+                let recorded = baseline.take(&fingerprint, &shown, v.line, &v.word);
+                summary.suppressed.baseline += usize::from(recorded);
+                !recorded
+            });
+            checked_against_baseline.insert(shown);
+        }
+        if let Some(changed_lines) = &changed_lines {
+            let changed = changed_lines.in_file(path);
+            let on_changed_line = |finding: &Finding| {
+                changed
                     .iter()
-                    .rposition(|b| *b == '\n' as u8 || *b == '\r' as u8)
-                    .map(|b| b + 1)
-                    .unwrap_or(0);
-                let slice_after = &mmap_slice[end_byte..];
-                let line_last_char = end_byte
-                    + slice_after
+                    .any(|lines| lines.start <= finding.end_line && finding.line < lines.end)
+            };
+            violations.retain(on_changed_line);
+            near_misses.retain(|(near_miss, _)| on_changed_line(near_miss));
+        }
+
+        // archive entries and inline source have nowhere to write fixes or markers to
+        if args.fix && matches!(source, Source::File(_)) {
+            let edits: Vec<Edit> = violations.iter().filter_map(|v| v.fix.clone()).collect();
+            // a fix only clears its violation once it's written, which a dry run never is
+            if !args.dry_run {
+                violations.retain(|v| v.fix.is_none());
+            }
+            if !edits.is_empty() {
+                summary.fixes.push((path.to_path_buf(), edits));
+            }
+        }
+        if args.annotate && !violations.is_empty() && matches!(source, Source::File(_)) {
+            let line_ends = violations.iter().map(|v| v.line_span.end).collect();
+            summary.annotations.push((path.to_path_buf(), line_ends));
+        }
+
+        if args.exec.is_some() && !violations.is_empty() && matches!(source, Source::File(_)) {
+            summary.offending_files.push(path.to_path_buf());
+        }
+
+        if args.review && !violations.is_empty() {
+            let lines: Vec<&[u8]> = mmap_slice
+                .split(|b| *b == b'\n')
+                .map(|line| line.strip_suffix(b"\r").unwrap_or(line))
+                .collect();
+            for violation in &violations {
+                let row = violation.line - 1;
+                let first_context_row = row.saturating_sub(CONTEXT_LINES);
+                let last_context_row = (row + CONTEXT_LINES).min(lines.len() - 1);
+                summary.review_items.push(ReviewItem {
+                    path: path.to_path_buf(),
+                    row,
+                    column: violation.column,
+                    text: violation.identifier.clone(),
+                    word: violation.word.clone(),
+                    severity: violation.severity,
+                    context: lines[first_context_row..=last_context_row]
                         .iter()
-                        .position(|b| *b == '\n' as u8 || *b == '\r' as u8)
-                        .unwrap_or(mmap_slice.len());
+                        .map(|line| String::from_utf8_lossy(line).into_owned())
+                        .collect(),
+                    first_context_row,
+                });
+            }
+        }
+
+        for violation in &violations {
+            summary.count(violation.severity);
+            if let Some(format) = finding_format {
+                let finding = json::Finding {
+                    path: shown.display().to_string(),
+                    line: violation.line,
+                    column: anchor_column(mmap_slice, violation, args),
+                    start_byte: violation.span.start,
+                    end_byte: violation.span.end,
+                    text: violation.identifier.clone(),
+                    word: violation.word.clone(),
+                    canonical: violation.canonical.clone(),
+                    severity: violation.severity,
+                    kind: violation.kind,
+                    label: violation.label.clone(),
+                    fingerprint: violation_fingerprint(
+                        &shown.display().to_string(),
+                        mmap_slice,
+                        violation,
+                    ),
+                };
+                let start = Point::new(violation.line - 1, violation.column);
+                let end = Point::new(violation.end_line - 1, violation.end_column);
+                match format {
+                    Format::Json => summary.findings.push(finding),
+                    Format::Ndjson => json::write_finding(&mut output.stdout, &finding)?,
+                    Format::Rdjson => summary.diagnostics.push(Diagnostic::new(
+                        &finding,
+                        &violation.explanation,
+                        start,
+                        end,
+                    )),
+                    Format::Sarif => summary.sarif_results.push(SarifResult::new(
+                        &finding,
+                        &violation.explanation,
+                        mmap_slice,
+                        violation.span.clone(),
+                        start,
+                        end,
+                    )),
+                    Format::Human => unreachable!("human output isn't structured"),
+                }
+            }
+            if args.report_unused {
+                summary.used_entries.insert(violation.word.clone());
+            }
+            if args.report.is_some() {
+                let hits = summary
+                    .word_hits
+                    .entry(violation.canonical.clone())
+                    .or_default();
+                hits.count += 1;
+                if hits.examples.len() < args.examples {
+                    hits.examples
+                        .push(line_anchor(&shown, mmap_slice, violation, args));
+                }
+            }
+        }
+        if quiet {
+            continue;
+        }
+
+        if !violations.is_empty() && summary.violations() == violations.len() {
+            writeln!(output.stderr, "ERROR: Banned identifiers found")?;
+            writeln!(output.stderr, "Found the following issues:")?;
+        }
+
+        if args.unique_lines {
+            for line in violations.chunk_by(|a, b| a.line == b.line) {
+                report_line(output, &shown, mmap_slice, line, args)?;
+            }
+        } else {
+            for violation in &violations {
+                report_line(
+                    output,
+                    &shown,
+                    mmap_slice,
+                    std::slice::from_ref(violation),
+                    args,
+                )?;
+            }
+        }
+
+        if args.explain_near_misses {
+            for (near_miss, exception) in &near_misses {
+                writeln!(
+                    output.stderr,
+                    "({}) note: `{}` matched {} but exception `!{}` applies",
+                    line_anchor(&shown, mmap_slice, near_miss, args),
+                    near_miss.identifier,
+                    near_miss.explanation,
+                    exception
+                )?;
+            }
+        }
+    }
+
+    if skipped_files > 0 && !quiet {
+        writeln!(
+            output.stderr,
+            "warning: skipped {} file(s) that could not be read",
+            skipped_files
+        )?;
+    }
+
+    // a violation in a file that wasn't checked may well still be there
+    if let Some(baseline) = &baseline {
+        summary.gone_from_baseline = baseline
+            .untaken()
+            .into_iter()
+            .filter(|finding| {
+                checked_against_baseline.contains(&finding.path)
+                    || !Path::new(&finding.path).exists()
+            })
+            .map(|finding| format!("{}:{}: {}", finding.path, finding.line, finding.word))
+            .collect();
+    }
+
+    if let Some(cache) = cache {
+        cache.save()?;
+    }
+    Ok(summary)
+}
+
+/// Drops the violations of `checked` allowed by the exemptions file, counting each
+/// violation left unreported, whether by it or by the checker, in `suppressed`.
+fn retain_reportable(checked: &mut Checked, exemptions: &Exemptions, suppressed: &mut Suppressed) {
+    let exempt = |finding: &Finding| {
+        exemptions.contains(&finding.path, finding.line - 1, &finding.identifier)
+    };
+    for (finding, suppression) in &checked.suppressed {
+        let counter = if exempt(finding) {
+            &mut suppressed.exemptions
+        } else {
+            match suppression {
+                Suppression::Marker => &mut suppressed.markers,
+                Suppression::Comment => &mut suppressed.comments,
+                Suppression::Allowance => &mut suppressed.allowances,
+            }
+        };
+        *counter += 1;
+    }
+    checked.findings.retain(|finding| {
+        let exempt = exempt(finding);
+        suppressed.exemptions += usize::from(exempt);
+        !exempt
+    });
+}
+
+fn violations_per_word(violations: &[Finding], counts: &mut HashMap<String, usize>) {
+    for violation in violations {
+        *counts.entry(violation.canonical.clone()).or_default() += 1;
+    }
+}
+
+/// Sorts out the violations of every source once ahead of reporting, to count the
+/// violations of each word for `--occurrence-scope run`. Sources that couldn't be read
+/// or parsed are left for the reporting to warn about.
+fn count_violations(
+    checkers: &[Checker],
+    sources: &[Source],
+    prepared: &[Prepared],
+    for_source: &[usize],
+    exemptions: &Exemptions,
+) -> Result<HashMap<String, usize>, TabooError> {
+    let mut counts = HashMap::new();
+    for ((source, set), prepared) in sources.iter().zip(for_source).zip(prepared) {
+        let Prepared::Found { mmap, found, .. } = prepared else {
+            continue;
+        };
+        let (path, bytes) = source_contents(source, mmap.as_ref());
+        let mut checked = checkers[*set].check_found(path, bytes, found)?;
+        retain_reportable(&mut checked, exemptions, &mut Suppressed::default());
+        violations_per_word(&checked.findings, &mut counts);
+    }
+    Ok(counts)
+}
+
+/// The `fingerprint` of `violation` in the file reported as `shown`.
+fn violation_fingerprint(shown: &str, source: &[u8], violation: &Finding) -> String {
+    fingerprint(
+        shown,
+        &violation.word,
+        &violation.identifier,
+        &source[violation.line_span.clone()],
+    )
+}
+
+/// Formats `path:line:column` for `finding` in `path`, per `--relative-line-anchors`.
+fn line_anchor(path: &Path, source: &[u8], finding: &Finding, args: &Args) -> String {
+    let path = path.display().to_string();
+    let path = if args.relative_line_anchors {
+        path.replace('/', std::path::MAIN_SEPARATOR_STR)
+    } else {
+        path
+    };
+
+    format!(
+        "{}:{}:{}",
+        path,
+        finding.line,
+        anchor_column(source, finding, args)
+    )
+}
 
-                let pre_banned = &mmap_slice[line_first_char..start_byte];
-                let post_banned = &mmap_slice[end_byte..line_last_char];
+/// The reported column of `finding`, per `--tab-width` and `--relative-line-anchors`.
+fn anchor_column(source: &[u8], finding: &Finding, args: &Args) -> usize {
+    let prefix = &source[finding.line_span.start..finding.span.start];
+    let column = match args.tab_width {
+        // columns stay byte offsets on lines without a tab before the violation
+        Some(tab_width) if prefix.contains(&b'\t') => {
+            let tab_width = tab_width.get();
+            String::from_utf8_lossy(prefix)
+                .chars()
+                .fold(0, |column, c| match c {
+                    '\t' => (column / tab_width + 1) * tab_width,
+                    _ => column + 1,
+                })
+        }
+        _ => finding.column,
+    };
 
-                eprintln!(
-                    "({}:{}:{}) {}{}{}",
-                    path.display(),
-                    capture.node.start_position().row + 1,
-                    capture.node.start_position().column,
-                    String::from_utf8_lossy(pre_banned),
-                    text.bright_red().bold(),
-                    String::from_utf8_lossy(post_banned)
-                )
+    if args.relative_line_anchors {
+        column + 1
+    } else {
+        column
+    }
+}
+
+/// Reports the line containing `violations`, which must all lie on that line in order.
+fn report_line(
+    output: &mut Output,
+    path: &Path,
+    source: &[u8],
+    violations: &[Finding],
+    args: &Args,
+) -> anyhow::Result<()> {
+    let first = &violations[0];
+    let line_first_char = first.line_span.start;
+    let line_last_char = violations[violations.len() - 1].line_span.end;
+
+    let mut labels = String::new();
+    let mut highlighted = String::new();
+    let mut word_tags = String::new();
+    let mut cursor = line_first_char;
+    let mut explanations = String::new();
+    for violation in violations {
+        let severity = match violation.severity {
+            Severity::Warning => Some("warning"),
+            Severity::Error => None,
+        };
+        for label in severity.into_iter().chain(violation.label.as_deref()) {
+            let tag = format!("[{label}] ");
+            if !labels.contains(&tag) {
+                labels.push_str(&tag);
             }
         }
+
+        if args.show_word {
+            let tag = format!(" [{}]", violation.word);
+            if !word_tags.contains(&tag) {
+                word_tags.push_str(&tag);
+            }
+        }
+
+        if args.explain {
+            explanations.push_str(&format!(
+                "\n    = `{}` at column {}: {}, no exception applies, reported as {}",
+                violation.identifier,
+                anchor_column(source, violation, args),
+                violation.explanation,
+                match violation.severity {
+                    Severity::Error => "an error",
+                    Severity::Warning => "a warning",
+                }
+            ));
+        }
+
+        // don't highlight bytes that an earlier occurrence already covered
+        let Range { start, end } = violation.matched_span;
+        if start < cursor {
+            continue;
+        }
+        highlighted.push_str(&String::from_utf8_lossy(&source[cursor..start]));
+        highlighted.push_str(
+            &String::from_utf8_lossy(&source[start..end])
+                .bright_red()
+                .bold()
+                .to_string(),
+        );
+        cursor = end;
     }
+    highlighted.push_str(&String::from_utf8_lossy(&source[cursor..line_last_char]));
+
+    let occurrences = if violations.len() > 1 {
+        format!(" ({} occurrences)", violations.len())
+    } else {
+        String::new()
+    };
+
+    writeln!(
+        output.stderr,
+        "({}) {}{}{}{}{}",
+        line_anchor(path, source, first, args),
+        labels,
+        highlighted,
+        occurrences,
+        word_tags,
+        explanations
+    )?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use haskell_taboo::banned_words_from;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    /// A stream of [`Output`] whose bytes can still be read once it's been written to.
+    #[derive(Clone, Default)]
+    struct Buffer(Rc<RefCell<Vec<u8>>>);
 
-    Ok(seen_banned_word)
+    impl Write for Buffer {
+        fn write(&mut self, bytes: &[u8]) -> std::io::Result<usize> {
+            self.0.borrow_mut().write(bytes)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// What scanning `source` against `list` with the options `flags` writes to
+    /// stdout and stderr.
+    fn streams(list: &str, source: &str, flags: &[&str]) -> (String, String) {
+        let args = Args::parse_from(["haskell-taboo", "taboo.txt"].iter().chain(flags));
+        let taboo_set = banned_words_from(list.as_bytes()).unwrap();
+        let sources = [Source::Memory(
+            PathBuf::from("A.hs"),
+            source.as_bytes().to_vec(),
+        )];
+        let (stdout, stderr) = (Buffer::default(), Buffer::default());
+        let mut output = Output {
+            stdout: Box::new(stdout.clone()),
+            stderr: Box::new(stderr.clone()),
+        };
+        check_paths_for_banned_words(
+            Path::new("taboo.txt"),
+            &taboo_set,
+            &sources,
+            &args,
+            &mut output,
+        )
+        .unwrap();
+        let text = |buffer: Buffer| String::from_utf8(buffer.0.take()).unwrap();
+        (text(stdout), text(stderr))
+    }
+
+    #[test]
+    fn violations_are_reported_on_stderr() {
+        let (stdout, stderr) = streams("foo\n", "f = foo\n", &[]);
+        assert_eq!(stdout, "");
+        assert!(stderr.contains("Banned identifiers found"));
+        assert!(stderr.contains("foo"));
+    }
+
+    #[test]
+    fn structured_findings_are_written_to_stdout() {
+        let (stdout, stderr) = streams("foo\n", "f = foo\n", &["--format", "ndjson"]);
+        assert!(stdout.contains("\"foo\""));
+        assert_eq!(stderr, "");
+    }
 }
@@ -0,0 +1,413 @@
+use crate::error::CliError;
+use globset::GlobBuilder;
+use ignore::overrides::OverrideBuilder;
+use ignore::{WalkBuilder, WalkState};
+use std::borrow::Cow;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Mutex;
+
+/// Whether `path` names a Haskell source file, ending in `.hs`, or a literate one,
+/// ending in `.lhs`.
+pub fn is_haskell_source(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext == "hs" || ext == "lhs")
+}
+
+/// Collects every Haskell source file beneath `root` and all of its subdirectories,
+/// walking directories in parallel and skipping those matching any of the gitignore-style
+/// `exclude` globs, as well as anything ignored by git.
+///
+/// The walker visits entries in whatever order its threads reach them, so the
+/// result is sorted to keep scans and their output deterministic.
+pub fn walk_files(root: &Path, exclude: &[String]) -> Result<Vec<PathBuf>, CliError> {
+    let paths = Mutex::new(Vec::new());
+    let error = Mutex::new(None);
+
+    let walk_error = |source| CliError::SourceDir {
+        root: root.to_path_buf(),
+        source,
+    };
+    let mut overrides = OverrideBuilder::new(root);
+    for pattern in exclude {
+        overrides
+            .add(&format!("!{}", pattern))
+            .map_err(|source| CliError::InvalidExclude {
+                pattern: pattern.clone(),
+                source,
+            })?;
+    }
+    let overrides = overrides.build().map_err(walk_error)?;
+
+    WalkBuilder::new(root)
+        .overrides(overrides)
+        .build_parallel()
+        .run(|| {
+            Box::new(|entry| match entry {
+                Ok(entry) => {
+                    let is_file = entry.file_type().is_some_and(|t| t.is_file());
+                    if is_file && is_haskell_source(entry.path()) {
+                        paths.lock().unwrap().push(entry.into_path());
+                    }
+                    WalkState::Continue
+                }
+                Err(e) => {
+                    error.lock().unwrap().get_or_insert(e);
+                    WalkState::Quit
+                }
+            })
+        });
+
+    if let Some(source) = error.into_inner().unwrap() {
+        return Err(walk_error(source));
+    }
+
+    let mut paths = paths.into_inner().unwrap();
+    paths.sort();
+    Ok(paths)
+}
+
+/// Whether a file argument is a glob pattern like `app/**/*.hs` to expand, rather than
+/// a path, which it still is if a file by that name exists.
+pub fn is_glob(arg: &str) -> bool {
+    has_glob_chars(arg) && !Path::new(arg).exists()
+}
+
+fn has_glob_chars(text: &str) -> bool {
+    text.contains(['*', '?', '[', '{'])
+}
+
+/// Collects the Haskell source files matching the glob `pattern`, walking only the
+/// directory named by its leading literal components, like `app` for `app/**/*.hs`.
+///
+/// If that directory doesn't exist, the pattern is as much a mistake as a file argument
+/// that doesn't exist, and fails the same way.
+pub fn glob_files(pattern: &str, exclude: &[String]) -> Result<Vec<PathBuf>, CliError> {
+    // `./app/**/*.hs` should match the same files as `app/**/*.hs`
+    let normalized = pattern.trim_start_matches("./");
+    let matcher = GlobBuilder::new(normalized)
+        .literal_separator(true)
+        .build()
+        .map_err(|source| CliError::InvalidGlob {
+            pattern: pattern.to_string(),
+            source,
+        })?
+        .compile_matcher();
+
+    let root: PathBuf = Path::new(normalized)
+        .components()
+        .take_while(|component| !has_glob_chars(&component.as_os_str().to_string_lossy()))
+        .collect();
+    let root = if root.as_os_str().is_empty() {
+        PathBuf::from(".")
+    } else {
+        root
+    };
+    if !root.is_dir() {
+        return Err(CliError::MissingPath {
+            path: PathBuf::from(pattern),
+        });
+    }
+
+    let mut paths = walk_files(&root, exclude)?;
+    // `./A.hs` should match `*.hs` like `A.hs` does
+    paths.retain(|path| matcher.is_match(path.strip_prefix(".").unwrap_or(path)));
+    Ok(paths)
+}
+
+/// Returns the `hs-source-dirs` of every component described by a `.cabal` file in
+/// `dir`, or `None` if there isn't one.
+pub fn cabal_source_dirs(dir: &Path) -> Result<Option<Vec<PathBuf>>, CliError> {
+    let Some(cabal_file) = std::fs::read_dir(dir)
+        .map_err(|source| CliError::ProjectFile {
+            path: dir.to_path_buf(),
+            source,
+        })?
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .find(|path| path.extension().is_some_and(|e| e == "cabal") && path.is_file())
+    else {
+        return Ok(None);
+    };
+
+    let contents =
+        std::fs::read_to_string(&cabal_file).map_err(|source| CliError::ProjectFile {
+            path: cabal_file.clone(),
+            source,
+        })?;
+
+    Ok(Some(
+        hs_source_dirs(&contents)
+            .into_iter()
+            .map(|source_dir| resolve_in(dir, &source_dir))
+            .collect(),
+    ))
+}
+
+/// The stanzas of a `.cabal` file describing components with sources of their own.
+const COMPONENT_STANZAS: [&str; 5] = [
+    "library",
+    "executable",
+    "test-suite",
+    "benchmark",
+    "foreign-library",
+];
+
+/// Collects the values of every `hs-source-dirs` field, including continuation lines.
+///
+/// A component without the field has its sources in the package directory, as cabal
+/// defaults to, unless it imports a `common` stanza, which may well have the field.
+fn hs_source_dirs(contents: &str) -> Vec<String> {
+    let mut source_dirs = Vec::new();
+    // the indentation of the `hs-source-dirs` field currently being read, if any
+    let mut field_indent = None;
+    // whether the component being read has, or may have, its own source directories
+    let mut in_component = None;
+
+    for line in contents.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.is_empty() || trimmed.starts_with("--") {
+            continue;
+        }
+        let indent = line.len() - trimmed.len();
+
+        if indent == 0 {
+            if in_component == Some(false) {
+                source_dirs.push(".".to_string());
+            }
+            let stanza = trimmed.split_whitespace().next().unwrap_or_default();
+            in_component = COMPONENT_STANZAS
+                .iter()
+                .any(|component| stanza.eq_ignore_ascii_case(component))
+                .then_some(false);
+        }
+
+        let value = match field_indent {
+            Some(field) if indent > field => trimmed,
+            _ => {
+                field_indent = None;
+                let Some((name, value)) = trimmed.split_once(':') else {
+                    continue;
+                };
+                let name = name.trim();
+                let is_source_dirs = name.eq_ignore_ascii_case("hs-source-dirs");
+                if in_component.is_some() && (is_source_dirs || name.eq_ignore_ascii_case("import"))
+                {
+                    in_component = Some(true);
+                }
+                if !is_source_dirs {
+                    continue;
+                }
+                field_indent = Some(indent);
+                value
+            }
+        };
+
+        source_dirs.extend(
+            value
+                .split(|c: char| c == ',' || c.is_whitespace())
+                .map(|d| d.trim_matches('"'))
+                .filter(|d| !d.is_empty())
+                .map(str::to_string),
+        );
+    }
+    if in_component == Some(false) {
+        source_dirs.push(".".to_string());
+    }
+
+    source_dirs.sort();
+    source_dirs.dedup();
+    source_dirs
+}
+
+/// Returns the `source-dirs` of every component described by an hpack `package.yaml`
+/// in `dir`, or `None` if there isn't one. Without any, the sources are in `dir`, as
+/// hpack defaults to.
+pub fn hpack_source_dirs(dir: &Path) -> Result<Option<Vec<PathBuf>>, CliError> {
+    let package_yaml = dir.join("package.yaml");
+    if !package_yaml.is_file() {
+        return Ok(None);
+    }
+
+    let contents =
+        std::fs::read_to_string(&package_yaml).map_err(|source| CliError::ProjectFile {
+            path: package_yaml.clone(),
+            source,
+        })?;
+
+    let mut source_dirs = source_dirs(&contents);
+    if source_dirs.is_empty() {
+        source_dirs.push(".".to_string());
+    }
+    Ok(Some(
+        source_dirs
+            .into_iter()
+            .map(|source_dir| resolve_in(dir, &source_dir))
+            .collect(),
+    ))
+}
+
+/// Collects the values of every `source-dirs` key, whether written as a scalar, a flow
+/// sequence or a block sequence.
+fn source_dirs(contents: &str) -> Vec<String> {
+    let mut source_dirs = Vec::new();
+    // the indentation of the `source-dirs` key whose block sequence is being read, if any
+    let mut key_indent = None;
+
+    for line in contents.lines() {
+        let line = line.split(" #").next().unwrap_or_default();
+        let trimmed = line.trim_start();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        let indent = line.len() - trimmed.len();
+
+        if let Some(key) = key_indent {
+            if let Some(item) = trimmed.strip_prefix('-').filter(|_| indent >= key) {
+                source_dirs.push(unquote(item));
+                continue;
+            }
+            key_indent = None;
+        }
+
+        let Some((key, value)) = trimmed.split_once(':') else {
+            continue;
+        };
+        if key.trim() != "source-dirs" {
+            continue;
+        }
+
+        let value = value.trim();
+        if value.is_empty() {
+            key_indent = Some(indent);
+        } else if let Some(items) = value.strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+            source_dirs.extend(items.split(',').map(unquote));
+        } else {
+            source_dirs.push(unquote(value));
+        }
+    }
+
+    source_dirs.retain(|d| !d.is_empty());
+    source_dirs.sort();
+    source_dirs.dedup();
+    source_dirs
+}
+
+fn unquote(value: &str) -> String {
+    value.trim().trim_matches(['"', '\'']).to_string()
+}
+
+/// Reads the files listed by the manifest at `manifest`, one path per line, skipping
+/// blank lines and `#` comments. Relative paths are relative to the manifest's directory,
+/// since whatever wrote it, like a build step listing its generated modules, needn't
+/// share the current one. Every listed file must exist.
+pub fn manifest_files(manifest: &Path) -> Result<Vec<PathBuf>, CliError> {
+    let contents = std::fs::read_to_string(manifest).map_err(|source| CliError::Manifest {
+        path: manifest.to_path_buf(),
+        source,
+    })?;
+    let dir = match manifest.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+
+    let mut files = Vec::new();
+    for (index, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let file = resolve_in(dir, line);
+        if !file.is_file() {
+            return Err(CliError::MissingManifestEntry {
+                manifest: manifest.to_path_buf(),
+                line: index + 1,
+                path: file,
+            });
+        }
+        files.push(file);
+    }
+    Ok(files)
+}
+
+/// Resolves a directory named by a package description in `dir`.
+fn resolve_in(dir: &Path, source_dir: &str) -> PathBuf {
+    if dir == Path::new(".") {
+        PathBuf::from(source_dir)
+    } else {
+        dir.join(source_dir)
+    }
+}
+
+/// Returns the top-level directory of the git work tree containing the current
+/// directory, or `None` outside of one or without git.
+pub fn git_root() -> Option<PathBuf> {
+    let output = Command::new("git")
+        .args(["rev-parse", "--show-toplevel"])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    let root = String::from_utf8(output.stdout).ok()?;
+    // canonical, so that it compares equal to the canonical paths of files inside it
+    PathBuf::from(root.trim_end()).canonicalize().ok()
+}
+
+/// Returns `path` relative to `root` if the file is inside it, or as given otherwise.
+///
+/// The file itself needn't exist, like a new file named by `--stdin-filename`, as
+/// long as its directory does.
+pub fn relative_to<'a>(path: &'a Path, root: &Path) -> Cow<'a, Path> {
+    let absolute = path.canonicalize().or_else(|e| {
+        let (Some(dir), Some(name)) = (path.parent(), path.file_name()) else {
+            return Err(e);
+        };
+        let dir = if dir.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            dir
+        };
+        dir.canonicalize().map(|dir| dir.join(name))
+    });
+    let Ok(absolute) = absolute else {
+        return Cow::Borrowed(path);
+    };
+    match absolute.strip_prefix(root) {
+        Ok(relative) => Cow::Owned(relative.to_path_buf()),
+        Err(_) => Cow::Borrowed(path),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn walk_files_collects_nested_modules_only() {
+        let dir = tempfile::tempdir().unwrap();
+        let nested = dir.path().join("src/Foo");
+        fs::create_dir_all(&nested).unwrap();
+        fs::write(nested.join("Bar.hs"), "module Foo.Bar where\n").unwrap();
+        fs::write(nested.join("notes.txt"), "not Haskell\n").unwrap();
+
+        let paths = walk_files(&dir.path().join("src"), &[]).unwrap();
+        assert_eq!(paths, [nested.join("Bar.hs")]);
+    }
+
+    #[test]
+    fn cabal_components_without_source_dirs_are_in_the_package_directory() {
+        let cabal = "name: p\n\
+                     library\n  hs-source-dirs: src\n\
+                     executable p\n  main-is: Main.hs\n\
+                     test-suite t\n  import: shared\n";
+        assert_eq!(hs_source_dirs(cabal), [".", "src"]);
+        assert_eq!(
+            hs_source_dirs("library\n  hs-source-dirs:\n    lib\n"),
+            ["lib"]
+        );
+    }
+}
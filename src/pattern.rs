@@ -0,0 +1,64 @@
+use regex::{RegexSet, RegexSetBuilder};
+use std::sync::OnceLock;
+
+/// Entries that match by regular expression, e.g. `re:^unsafe` or the glob `*Impl`, all
+/// checked against an identifier in a single pass however many there are.
+///
+/// The patterns are compiled together the first time they're matched against, so that
+/// inserting entries one at a time doesn't recompile them.
+#[derive(Debug, Clone)]
+pub struct PatternSet<T> {
+    patterns: Vec<String>,
+    entries: Vec<T>,
+    case_insensitive: bool,
+    compiled: OnceLock<RegexSet>,
+}
+
+impl<T> Default for PatternSet<T> {
+    fn default() -> Self {
+        PatternSet::new(false)
+    }
+}
+
+impl<T> PatternSet<T> {
+    pub fn new(case_insensitive: bool) -> Self {
+        PatternSet {
+            patterns: Vec::new(),
+            entries: Vec::new(),
+            case_insensitive,
+            compiled: OnceLock::new(),
+        }
+    }
+
+    /// Adds `entry` matched by `pattern`, which must be a valid regular expression.
+    pub fn insert(&mut self, pattern: String, entry: T) {
+        self.patterns.push(pattern);
+        self.entries.push(entry);
+        self.compiled = OnceLock::new();
+    }
+
+    /// Returns every entry whose pattern matches somewhere in `text`, in the order they
+    /// were inserted.
+    pub fn matching(&self, text: &str) -> impl Iterator<Item = &T> {
+        let matches: Vec<usize> = if self.entries.is_empty() {
+            Vec::new()
+        } else {
+            self.compiled().matches(text).into_iter().collect()
+        };
+        matches.into_iter().map(|index| &self.entries[index])
+    }
+
+    fn compiled(&self) -> &RegexSet {
+        self.compiled.get_or_init(|| {
+            RegexSetBuilder::new(&self.patterns)
+                .case_insensitive(self.case_insensitive)
+                .build()
+                .expect("patterns are validated before they're inserted")
+        })
+    }
+
+    /// Returns every entry, in the order they were inserted.
+    pub fn values(&self) -> impl Iterator<Item = &T> {
+        self.entries.iter()
+    }
+}
@@ -0,0 +1,205 @@
+use crate::error::CliError;
+use haskell_taboo::Severity;
+use ratatui::DefaultTerminal;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style, Stylize};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Paragraph};
+use std::collections::HashSet;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+
+/// Lines of source shown above and below a violation under review.
+pub const CONTEXT_LINES: usize = 3;
+
+/// A violation kept after scanning so that it can be reviewed.
+#[derive(Debug)]
+pub struct ReviewItem {
+    pub path: PathBuf,
+    /// The 0-based row and byte column of the violation.
+    pub row: usize,
+    pub column: usize,
+    pub text: String,
+    pub word: String,
+    pub severity: Severity,
+    /// The violation's line and those around it, starting at row `first_context_row`.
+    pub context: Vec<String>,
+    pub first_context_row: usize,
+}
+
+/// What became of the violations presented by [`review`].
+#[derive(Debug, Default)]
+pub struct ReviewOutcome {
+    pub allowed: Vec<usize>,
+    pub skipped: usize,
+}
+
+/// Violations allowed by an exemptions file, one `path:line:identifier` per line.
+#[derive(Debug, Default)]
+pub struct Exemptions {
+    entries: HashSet<String>,
+}
+
+impl Exemptions {
+    /// Loads `path`, which is treated as empty if it doesn't exist yet.
+    pub fn load(path: &Path) -> Result<Self, CliError> {
+        let error = |source| CliError::Exemptions {
+            path: path.to_path_buf(),
+            source,
+        };
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(error(e)),
+        };
+
+        let mut entries = HashSet::new();
+        for line in BufReader::new(file).lines() {
+            let line = line.map_err(error)?;
+            let line = line.trim();
+            if !line.is_empty() {
+                entries.insert(line.to_string());
+            }
+        }
+        Ok(Exemptions { entries })
+    }
+
+    /// Whether `text` at the 0-based `row` of `path` is exempt.
+    pub fn contains(&self, path: &Path, row: usize, text: &str) -> bool {
+        !self.entries.is_empty() && self.entries.contains(&exemption(path, row, text))
+    }
+}
+
+fn exemption(path: &Path, row: usize, text: &str) -> String {
+    format!("{}:{}:{}", path.display(), row + 1, text)
+}
+
+/// Steps through `items` in a terminal UI, appending each one marked as allowed to
+/// the exemptions file at `exemptions`.
+pub fn review(items: &[ReviewItem], exemptions: &Path) -> anyhow::Result<ReviewOutcome> {
+    if !std::io::stdin().is_terminal() || !std::io::stdout().is_terminal() {
+        return Err(CliError::NoTerminal.into());
+    }
+    let mut exemptions_file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(exemptions)
+        .map_err(|source| CliError::Exemptions {
+            path: exemptions.to_path_buf(),
+            source,
+        })?;
+
+    let mut terminal = ratatui::try_init().map_err(|source| {
+        // raw mode may have been enabled before setting up the terminal failed
+        ratatui::restore();
+        CliError::Terminal { source }
+    })?;
+    let outcome = review_in(&mut terminal, items, &mut exemptions_file);
+    ratatui::restore();
+    outcome
+}
+
+fn review_in(
+    terminal: &mut DefaultTerminal,
+    items: &[ReviewItem],
+    exemptions_file: &mut File,
+) -> anyhow::Result<ReviewOutcome> {
+    let mut outcome = ReviewOutcome::default();
+    let mut index = 0;
+
+    while let Some(item) = items.get(index) {
+        terminal.draw(|frame| {
+            let [header, body, footer] = Layout::vertical([
+                Constraint::Length(1),
+                Constraint::Min(0),
+                Constraint::Length(1),
+            ])
+            .areas(frame.area());
+
+            frame.render_widget(
+                Line::from(format!(
+                    "[{}/{}] {}:{}:{}  `{}` matched `{}`{}",
+                    index + 1,
+                    items.len(),
+                    item.path.display(),
+                    item.row + 1,
+                    item.column,
+                    item.text,
+                    item.word,
+                    if item.severity == Severity::Warning {
+                        " (warning)"
+                    } else {
+                        ""
+                    }
+                ))
+                .bold(),
+                header,
+            );
+            frame.render_widget(
+                Paragraph::new(context_lines(item)).block(Block::bordered()),
+                body,
+            );
+            frame.render_widget(
+                Line::from("a: allow   s: skip   q: quit").dark_gray(),
+                footer,
+            );
+        })?;
+
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+
+        match key.code {
+            KeyCode::Char('a') => {
+                writeln!(
+                    exemptions_file,
+                    "{}",
+                    exemption(&item.path, item.row, &item.text)
+                )?;
+                outcome.allowed.push(index);
+            }
+            KeyCode::Char('s') | KeyCode::Char(' ') | KeyCode::Enter => outcome.skipped += 1,
+            KeyCode::Char('q') | KeyCode::Esc => break,
+            _ => continue,
+        }
+        index += 1;
+    }
+
+    Ok(outcome)
+}
+
+/// Renders the context of `item` with line numbers, highlighting the violation.
+fn context_lines(item: &ReviewItem) -> Vec<Line<'_>> {
+    let highlight = Style::new()
+        .fg(Color::LightRed)
+        .add_modifier(Modifier::BOLD);
+
+    item.context
+        .iter()
+        .enumerate()
+        .map(|(offset, line)| {
+            let row = item.first_context_row + offset;
+            let number = Span::raw(format!("{:>5} | ", row + 1)).dark_gray();
+            if row != item.row {
+                return Line::from(vec![number, Span::raw(line.as_str())]);
+            }
+
+            let start = item.column.min(line.len());
+            let end = (start + item.text.len()).min(line.len());
+            if !line.is_char_boundary(start) || !line.is_char_boundary(end) {
+                return Line::from(vec![number, Span::raw(line.as_str())]);
+            }
+            Line::from(vec![
+                number,
+                Span::raw(&line[..start]),
+                Span::styled(&line[start..end], highlight),
+                Span::raw(&line[end..]),
+            ])
+        })
+        .collect()
+}
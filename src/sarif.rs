@@ -0,0 +1,231 @@
+use crate::json::{Finding, TOOL};
+use haskell_taboo::Severity;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::ops::Range;
+use tree_sitter::Point;
+
+const SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+const VERSION: &str = "2.1.0";
+
+/// The key of a result's fingerprint in `partialFingerprints`, versioned like SARIF
+/// recommends in case the way fingerprints are computed ever changes.
+const FINGERPRINT_KEY: &str = "tabooFingerprint/v1";
+
+/// A violation as a SARIF 2.1.0 result, for `--format sarif`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SarifResult {
+    rule_id: String,
+    /// Filled in by [`write_sarif`] once every rule is known.
+    rule_index: usize,
+    level: Level,
+    message: Message,
+    locations: [ResultLocation; 1],
+    partial_fingerprints: BTreeMap<&'static str, String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+enum Level {
+    Error,
+    Warning,
+}
+
+impl From<Severity> for Level {
+    fn from(severity: Severity) -> Self {
+        match severity {
+            Severity::Error => Level::Error,
+            Severity::Warning => Level::Warning,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct Message {
+    text: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ResultLocation {
+    physical_location: PhysicalLocation,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PhysicalLocation {
+    artifact_location: ArtifactLocation,
+    region: Region,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ArtifactLocation {
+    uri: String,
+    /// Set for relative paths, which code scanning resolves against the checkout.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    uri_base_id: Option<&'static str>,
+}
+
+/// A 1-based span of lines and columns, where columns count Unicode code points as the
+/// run's `columnKind` says, along with the span's bytes.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Region {
+    start_line: usize,
+    start_column: usize,
+    end_line: usize,
+    end_column: usize,
+    byte_offset: usize,
+    byte_length: usize,
+}
+
+impl SarifResult {
+    /// Describes `finding`, which spans `bytes` of `source` from `start` to `end`, with
+    /// `explanation` of how it matched.
+    pub fn new(
+        finding: &Finding,
+        explanation: &str,
+        source: &[u8],
+        bytes: Range<usize>,
+        start: Point,
+        end: Point,
+    ) -> Self {
+        let path = finding.path.replace('\\', "/");
+        let is_relative = !path.starts_with('/') && !path.contains(':');
+        SarifResult {
+            rule_id: finding.word.clone(),
+            rule_index: 0,
+            level: finding.severity.into(),
+            message: Message {
+                text: format!("banned identifier `{}`: {}", finding.text, explanation),
+            },
+            locations: [ResultLocation {
+                physical_location: PhysicalLocation {
+                    artifact_location: ArtifactLocation {
+                        uri: encode_uri(&path),
+                        uri_base_id: is_relative.then_some("%SRCROOT%"),
+                    },
+                    region: Region {
+                        start_line: start.row + 1,
+                        start_column: code_point_column(source, bytes.start, start),
+                        end_line: end.row + 1,
+                        end_column: code_point_column(source, bytes.end, end),
+                        byte_offset: bytes.start,
+                        byte_length: bytes.len(),
+                    },
+                },
+            }],
+            partial_fingerprints: BTreeMap::from([(FINGERPRINT_KEY, finding.fingerprint.clone())]),
+        }
+    }
+}
+
+/// The 1-based column, in code points, of `offset`, which is at `point`.
+fn code_point_column(source: &[u8], offset: usize, point: Point) -> usize {
+    let line_start = offset - point.column;
+    String::from_utf8_lossy(&source[line_start..offset])
+        .chars()
+        .count()
+        + 1
+}
+
+/// Percent-encodes the bytes of `path` that can't appear in a URI path as they are.
+fn encode_uri(path: &str) -> String {
+    let mut uri = String::with_capacity(path.len());
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                uri.push(char::from(byte))
+            }
+            _ => uri.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    uri
+}
+
+#[derive(Serialize)]
+struct Log<'a> {
+    #[serde(rename = "$schema")]
+    schema: &'static str,
+    version: &'static str,
+    runs: [Run<'a>; 1],
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Run<'a> {
+    tool: SarifTool,
+    column_kind: &'static str,
+    results: &'a [SarifResult],
+}
+
+#[derive(Serialize)]
+struct SarifTool {
+    driver: Driver,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Driver {
+    name: &'static str,
+    version: &'static str,
+    rules: Vec<Rule>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Rule {
+    id: String,
+    short_description: Message,
+    default_configuration: Configuration,
+}
+
+#[derive(Serialize)]
+struct Configuration {
+    level: Level,
+}
+
+/// Writes the whole `--format sarif` log, with a rule for each banned entry that was
+/// violated, which GitHub code scanning can take as it is.
+///
+/// A rule's default level is that of its first result, though each result still has
+/// its own, since `in` annotations and sections can ban an entry at either severity.
+pub fn write_sarif(out: &mut dyn Write, results: &mut [SarifResult]) -> io::Result<()> {
+    let mut rules: Vec<Rule> = Vec::new();
+    let mut indices: BTreeMap<String, usize> = BTreeMap::new();
+    for result in results.iter_mut() {
+        result.rule_index = *indices.entry(result.rule_id.clone()).or_insert_with(|| {
+            rules.push(Rule {
+                id: result.rule_id.clone(),
+                short_description: Message {
+                    text: format!("`{}` is banned", result.rule_id),
+                },
+                default_configuration: Configuration {
+                    level: result.level,
+                },
+            });
+            rules.len() - 1
+        });
+    }
+
+    let log = Log {
+        schema: SCHEMA,
+        version: VERSION,
+        runs: [Run {
+            tool: SarifTool {
+                driver: Driver {
+                    name: TOOL.name,
+                    version: TOOL.version,
+                    rules,
+                },
+            },
+            column_kind: "unicodeCodePoints",
+            results,
+        }],
+    };
+    serde_json::to_writer_pretty(&mut *out, &log)?;
+    writeln!(out)
+}
@@ -0,0 +1,887 @@
+use crate::TabooError;
+use crate::taboo::{MatchOptions, QuerySection, SectionQuery, TabooMatch, TabooSet, words};
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+use std::path::Path;
+use std::str::Utf8Error;
+use std::time::{Duration, Instant};
+use tree_sitter::{
+    Language, Node, ParseOptions, ParseState, Parser, Point, Query, QueryCursor, StreamingIterator,
+    Tree,
+};
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Only {
+    /// Names bound by top-level functions and bindings, or by `<-` in a do block,
+    /// ignoring references
+    Def,
+    /// Every occurrence that isn't a top-level definition
+    Use,
+}
+
+/// A context whose captures are never reported, whichever query captured them.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum IgnoreIn {
+    /// Line, block and Haddock comments
+    Comment,
+    /// String and character literals
+    String,
+    /// LANGUAGE, OPTIONS_GHC and other pragmas
+    Pragma,
+}
+
+impl IgnoreIn {
+    fn node_kinds(self) -> &'static [&'static str] {
+        match self {
+            IgnoreIn::Comment => &["comment", "haddock"],
+            IgnoreIn::String => &["string", "char"],
+            IgnoreIn::Pragma => &["pragma"],
+        }
+    }
+}
+
+/// A kind of identifier checked by entries outside of `[query]` sections, each reported
+/// with its own label unless it's a plain variable or operator.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Kind {
+    /// Variables and functions, including type variables
+    Variable,
+    /// Operators and constructor operators like `:+:`
+    Operator,
+    /// Data constructors and pattern synonyms
+    Constructor,
+    /// Type and type class names in signatures and declarations
+    Type,
+    /// Class names in declarations, instances, constraints and deriving clauses
+    Class,
+    /// Record fields where they're declared or updated
+    Field,
+    /// Module names in the header, imports and qualified names
+    Module,
+}
+
+impl Kind {
+    /// The kinds checked without `--kinds`.
+    pub const DEFAULT: [Kind; 5] = [
+        Kind::Variable,
+        Kind::Operator,
+        Kind::Constructor,
+        Kind::Type,
+        Kind::Class,
+    ];
+
+    fn patterns(self) -> &'static str {
+        match self {
+            Kind::Variable => "(variable) @variable-name",
+            Kind::Operator => "(operator) @operator-name (constructor_operator) @operator-name",
+            Kind::Constructor => {
+                "(pattern_synonym (equation synonym: [(constructor) @pattern-synonym \
+                    (apply function: (constructor) @pattern-synonym) \
+                    (record constructor: (constructor) @pattern-synonym) \
+                    (infix operator: (constructor_operator) @pattern-synonym)])) \
+                (constructor) @constructor"
+            }
+            Kind::Type => "(name) @type",
+            Kind::Class => {
+                "(context context: [(apply constructor: [(name) @constraint (qualified id: (name) @constraint)]) \
+                    (_ (apply constructor: [(name) @constraint (qualified id: (name) @constraint)]))]) \
+                (deriving classes: [(name) @deriving (qualified id: (name) @deriving) \
+                    (_ [(name) @deriving (qualified id: (name) @deriving)])]) \
+                (deriving_instance name: [(name) @deriving (qualified id: (name) @deriving)]) \
+                (class name: (name) @class) \
+                (instance name: [(name) @class (qualified id: (name) @class)])"
+            }
+            Kind::Field => "(field_name (variable) @field)",
+            Kind::Module => "(module) @module",
+        }
+    }
+}
+
+/// The labels of captures that only say what kind of identifier they are, which a more
+/// specific label for the same node, like `constraint` rather than `type`, takes over.
+const KIND_LABELS: [&str; 3] = ["constructor", "type", "module"];
+
+/// The kinds of nodes that are identifiers, which a `[query]` section may capture along
+/// with comments, strings and anything else.
+const IDENTIFIER_NODES: [&str; 6] = [
+    "variable",
+    "constructor",
+    "name",
+    "operator",
+    "constructor_operator",
+    "module",
+];
+
+/// How a [`Checker`](crate::Checker) finds occurrences and compares them with entries.
+#[derive(Debug, Clone, Default)]
+pub struct ScanOptions {
+    pub matching: MatchOptions,
+    pub only: Option<Only>,
+    pub include_pragmas: bool,
+    pub qualified: bool,
+    pub ignore_in: Vec<IgnoreIn>,
+    /// How long parsing a single source may take before it is given up on.
+    pub timeout: Option<Duration>,
+}
+
+/// Compiled queries, set up once and shared by every thread scanning sources.
+pub struct Scanner<'a> {
+    queries: Vec<ScanQuery<'a>>,
+    options: ScanOptions,
+}
+
+/// The banned words found in one source.
+pub struct Scan<'a> {
+    pub violations: Vec<Violation<'a>>,
+    pub near_misses: Vec<NearMiss<'a>>,
+    /// The module the source declares in its header, if it has one.
+    pub module: Option<&'a str>,
+}
+
+/// Where [`Checker::find`](crate::Checker::find) found banned words in one source, as
+/// offsets rather than borrows, so that it can be sent between threads and cached
+/// between runs.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Found {
+    module: Option<Range<usize>>,
+    occurrences: Vec<FoundOccurrence>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct FoundOccurrence {
+    /// The index of the query that captured the occurrence.
+    query: usize,
+    bytes: Range<usize>,
+    row: usize,
+    column: usize,
+    name: Range<usize>,
+    label: Option<String>,
+    identifier: bool,
+}
+
+/// A parser for Haskell, of which each thread scanning sources needs its own.
+pub fn new_parser(language: &Language) -> Parser {
+    let mut parser = Parser::new();
+    parser
+        .set_language(language)
+        .expect("Error loading grammar");
+    parser
+}
+
+impl<'a> Scanner<'a> {
+    pub fn new(queries: Vec<ScanQuery<'a>>, options: ScanOptions) -> Self {
+        Scanner { queries, options }
+    }
+
+    /// Parses `source`, the contents of the file at `path`, with `parser`, and finds the
+    /// occurrences that match a banned entry, whether or not an exception permits them.
+    ///
+    /// Every query runs against the same tree, so each source is parsed exactly once.
+    pub fn find(
+        &self,
+        parser: &mut Parser,
+        source: &[u8],
+        path: &Path,
+    ) -> Result<Found, TabooError> {
+        let tree = self.parse(parser, source, path)?;
+
+        let module = tree
+            .root_node()
+            .children(&mut tree.walk())
+            .find(|node| node.kind() == "header")
+            .and_then(|header| header.child_by_field_name("module"))
+            .map(|module| module.byte_range());
+        let mut found = Found {
+            module,
+            occurrences: Vec::new(),
+        };
+        for (index, scan_query) in self.queries.iter().enumerate() {
+            let mut occurrences = Vec::new();
+            collect_occurrences(scan_query, &tree, source, &self.options, &mut occurrences)
+                .map_err(|source| TabooError::InvalidUtf8 {
+                    path: path.to_path_buf(),
+                    source,
+                })?;
+
+            for occurrence in occurrences {
+                let Some(matched) = self.match_occurrence(scan_query, &occurrence, path) else {
+                    continue;
+                };
+                if !matched.arg_of.is_empty() {
+                    let node = tree
+                        .root_node()
+                        .descendant_for_byte_range(occurrence.start_byte, occurrence.end_byte);
+                    if !node.is_some_and(|node| is_argument_of(node, source, matched.arg_of)) {
+                        continue;
+                    }
+                }
+
+                let name_start = offset_within(source, occurrence.name);
+                found.occurrences.push(FoundOccurrence {
+                    query: index,
+                    bytes: occurrence.start_byte..occurrence.end_byte,
+                    row: occurrence.position.row,
+                    column: occurrence.position.column,
+                    name: name_start..name_start + occurrence.name.len(),
+                    label: occurrence.label.map(str::to_string),
+                    identifier: occurrence.identifier,
+                });
+            }
+        }
+
+        Ok(found)
+    }
+
+    /// Sorts what [`Scanner::find`] found in `source`, the contents of the file at
+    /// `path`, into violations and the near misses that exceptions permit.
+    pub fn scan<'s>(
+        &'s self,
+        source: &'s [u8],
+        path: &Path,
+        found: &'s Found,
+    ) -> Result<Scan<'s>, TabooError> {
+        let text = |bytes: &Range<usize>| {
+            std::str::from_utf8(&source[bytes.clone()]).map_err(|source| TabooError::InvalidUtf8 {
+                path: path.to_path_buf(),
+                source,
+            })
+        };
+
+        let mut scan = Scan {
+            violations: Vec::new(),
+            near_misses: Vec::new(),
+            module: found.module.as_ref().map(text).transpose()?,
+        };
+        for found_occurrence in &found.occurrences {
+            let scan_query = &self.queries[found_occurrence.query];
+            let occurrence = Occurrence {
+                start_byte: found_occurrence.bytes.start,
+                end_byte: found_occurrence.bytes.end,
+                position: Point::new(found_occurrence.row, found_occurrence.column),
+                text: text(&found_occurrence.bytes)?,
+                name: text(&found_occurrence.name)?,
+                label: found_occurrence.label.as_deref(),
+                identifier: found_occurrence.identifier,
+                rules: scan_query.rules,
+            };
+            let Some(matched) = self.match_occurrence(scan_query, &occurrence, path) else {
+                continue;
+            };
+
+            let rules = occurrence.rules;
+            let exception = rules
+                .exception_for(occurrence.text, self.options.matching)
+                .or_else(|| rules.exception_for(occurrence.name, self.options.matching));
+            match exception {
+                Some(exception) => scan.near_misses.push(NearMiss {
+                    occurrence,
+                    matched,
+                    exception,
+                }),
+                None => scan.violations.push(Violation {
+                    occurrence,
+                    matched,
+                }),
+            }
+        }
+
+        Ok(scan)
+    }
+
+    fn match_occurrence(
+        &self,
+        scan_query: &ScanQuery<'a>,
+        occurrence: &Occurrence,
+        path: &Path,
+    ) -> Option<TabooMatch<'a>> {
+        let rules = scan_query.rules;
+        if scan_query.modules {
+            rules.ban_for_module(occurrence.text, path, self.options.matching)
+        } else {
+            rules.ban_for_qualified(
+                occurrence.text,
+                occurrence.name,
+                path,
+                self.options.matching,
+            )
+        }
+    }
+
+    fn parse(&self, parser: &mut Parser, source: &[u8], path: &Path) -> Result<Tree, TabooError> {
+        let Some(timeout) = self.options.timeout else {
+            return parser.parse(source, None).ok_or_else(|| TabooError::Parse {
+                path: path.to_path_buf(),
+            });
+        };
+
+        let started = Instant::now();
+        let mut out_of_time = |_: &ParseState| started.elapsed() > timeout;
+        let tree = parser.parse_with_options(
+            &mut |offset, _| &source[offset..],
+            None,
+            Some(ParseOptions::new().progress_callback(&mut out_of_time)),
+        );
+
+        tree.ok_or_else(|| {
+            // a cancelled parse would otherwise be resumed by the next source
+            parser.reset();
+            TabooError::ParseTimeout {
+                path: path.to_path_buf(),
+                timeout_ms: timeout.as_millis() as u64,
+            }
+        })
+    }
+}
+
+/// The offset of `text` in `source`, which it must borrow from.
+fn offset_within(source: &[u8], text: &str) -> usize {
+    text.as_ptr() as usize - source.as_ptr() as usize
+}
+
+/// A span of a source file that could be a banned word.
+pub struct Occurrence<'a> {
+    pub start_byte: usize,
+    pub end_byte: usize,
+    pub position: Point,
+    pub text: &'a str,
+    /// The identifier itself, which differs from `text` when `text` is qualified.
+    pub name: &'a str,
+    pub label: Option<&'a str>,
+    /// Whether the occurrence is an identifier, rather than a word found in a comment,
+    /// string, pragma or other text, so that `--fix` can rewrite it.
+    pub identifier: bool,
+    /// The entries this occurrence is checked against.
+    pub rules: &'a TabooSet,
+}
+
+impl Occurrence<'_> {
+    /// The position just past the end of the occurrence.
+    pub fn end_position(&self) -> Point {
+        position_within(self.position, self.text, self.text.len())
+    }
+}
+
+/// An occurrence that matched a banned word.
+pub struct Violation<'a> {
+    pub occurrence: Occurrence<'a>,
+    pub matched: TabooMatch<'a>,
+}
+
+/// An occurrence that matched a banned word, but which an exception permits.
+pub struct NearMiss<'a> {
+    pub occurrence: Occurrence<'a>,
+    pub matched: TabooMatch<'a>,
+    pub exception: &'a str,
+}
+
+/// The bytes of the line holding `start..end`, excluding its line ending.
+pub fn line_around(source: &[u8], start: usize, end: usize) -> Range<usize> {
+    let line_start = source[..start]
+        .iter()
+        .rposition(|b| *b == b'\n' || *b == b'\r')
+        .map(|b| b + 1)
+        .unwrap_or(0);
+    let slice_after = &source[end..];
+    let line_end = end
+        + slice_after
+            .iter()
+            .position(|b| *b == b'\n' || *b == b'\r')
+            .unwrap_or(slice_after.len());
+    line_start..line_end
+}
+
+/// Whether `node` is a name introduced by a definition that `--only def` reports.
+fn is_definition(node: Node) -> bool {
+    is_top_level_definition(node) || is_do_bound(node)
+}
+
+/// Whether `node` is a name bound by the pattern of a do statement like `x <- action`,
+/// however deeply the pattern nests it, e.g. `(Just x, _) <- action`.
+fn is_do_bound(node: Node) -> bool {
+    let mut current = node;
+    while let Some(parent) = current.parent() {
+        match parent.kind() {
+            "bind" => {
+                return parent.child_by_field_name("pattern") == Some(current)
+                    && parent.parent().is_some_and(|p| p.kind() == "do");
+            }
+            // the function of a view pattern `(f -> x)` is used, not bound
+            "view_pattern" if parent.child_by_field_name("expression") == Some(current) => {
+                return false;
+            }
+            _ => current = parent,
+        }
+    }
+    false
+}
+
+/// Whether `node` is the name introduced by a top-level function or binding.
+fn is_top_level_definition(node: Node) -> bool {
+    let Some(mut parent) = node.parent() else {
+        return false;
+    };
+    let mut head = node;
+
+    // operators are defined either infix, `a <+> b = ...`, or in parentheses, `(<+>) a b = ...`
+    if parent.kind() == "prefix_id"
+        || (parent.kind() == "infix" && parent.child_by_field_name("operator") == Some(node))
+    {
+        head = parent;
+        let Some(grandparent) = parent.parent() else {
+            return false;
+        };
+        parent = grandparent;
+    }
+
+    let is_head = match parent.kind() {
+        "function" if head.kind() == "infix" => parent.child(0) == Some(head),
+        "function" | "bind" => parent.child_by_field_name("name") == Some(head),
+        _ => false,
+    };
+
+    is_head && parent.parent().is_some_and(|p| p.kind() == "declarations")
+}
+
+/// Whether `node` is, or is inside, a context excluded by `ignore_in`.
+fn is_ignored(node: Node, ignore_in: &[IgnoreIn]) -> bool {
+    if ignore_in.is_empty() {
+        return false;
+    }
+
+    let mut node = Some(node);
+    while let Some(current) = node {
+        let kind = current.kind();
+        if ignore_in
+            .iter()
+            .any(|ignored| ignored.node_kinds().contains(&kind))
+        {
+            return true;
+        }
+        node = current.parent();
+    }
+    false
+}
+
+/// Whether `node` is within an argument of an application of one of `functions`, like
+/// `password` in `log password`, `Log.log (show password)` or `log $ password`.
+fn is_argument_of(node: Node, source: &[u8], functions: &[String]) -> bool {
+    let mut current = node;
+    while let Some(parent) = current.parent() {
+        let function = match parent.kind() {
+            "apply" if parent.child_by_field_name("argument") == Some(current) => {
+                parent.child_by_field_name("function")
+            }
+            "infix"
+                if parent.child_by_field_name("right_operand") == Some(current)
+                    && parent
+                        .child_by_field_name("operator")
+                        .is_some_and(|op| op.utf8_text(source) == Ok("$")) =>
+            {
+                parent.child_by_field_name("left_operand")
+            }
+            _ => None,
+        };
+        if function.is_some_and(|function| applies_one_of(function, source, functions)) {
+            return true;
+        }
+        current = parent;
+    }
+    false
+}
+
+/// Whether the head of the possibly partial application `function`, like `log` in
+/// `log level`, is one of `functions`, either as written or without its qualifier.
+fn applies_one_of(mut function: Node, source: &[u8], functions: &[String]) -> bool {
+    while function.kind() == "apply" {
+        let Some(head) = function.child_by_field_name("function") else {
+            return false;
+        };
+        function = head;
+    }
+
+    let name = match function.kind() {
+        "variable" => Some(function),
+        "qualified" => function.child_by_field_name("id"),
+        _ => None,
+    };
+    let (Some(name), Ok(written)) = (name, function.utf8_text(source)) else {
+        return false;
+    };
+    let name = name.utf8_text(source).unwrap_or(written);
+    functions.iter().any(|f| f == written || f == name)
+}
+
+/// Finds the position of `offset` bytes into `text`, which itself starts at `start`.
+fn position_within(start: Point, text: &str, offset: usize) -> Point {
+    let prefix = &text.as_bytes()[..offset];
+    match prefix.iter().rposition(|b| *b == b'\n') {
+        Some(newline) => Point::new(
+            start.row + prefix.iter().filter(|b| **b == b'\n').count(),
+            offset - newline - 1,
+        ),
+        None => Point::new(start.row, start.column + offset),
+    }
+}
+
+/// The identifier query used for entries outside of any `[query ...]` section, checking
+/// identifiers of each of `kinds`.
+///
+/// Operators are matched whole in every form: prefix `(+)`, infix and sections `(+ 1)`
+/// `(1 +)`. Constructor operators like `:+:` are matched too, both as terms and as type
+/// operators in signatures and declarations, like `f :: (Maybe :+: []) a`. Quasiquote
+/// bodies and pragmas are opaque to the grammar, so their words are checked
+/// individually whatever the kinds, and Template Haskell constructs are captured only so
+/// that the identifiers inside them can be labelled. Class names are labelled by where
+/// they appear, like `constraint`, and pattern synonyms where they are declared.
+/// Functions applied in view patterns are ordinary variables, so they need no pattern of
+/// their own.
+fn identifier_query(kinds: &[Kind]) -> String {
+    let mut query: Vec<&str> = kinds.iter().map(|kind| kind.patterns()).collect();
+    query.push(
+        "(quasiquote_body) @quasiquote-body (pragma) @pragma \
+        [(splice) (top_splice) (quote) (typed_quote) (quasiquote) (th_quoted_name)] \
+        @template-haskell",
+    );
+    query.join(" ")
+}
+
+/// The query used for entries outside of any section with `--ban-modules`, capturing
+/// the module of every import, qualified or not.
+const MODULE_QUERY: &str = "(import module: (module) @import)";
+
+/// A compiled query and the entries its captures are checked against.
+pub struct ScanQuery<'a> {
+    query: Query,
+    rules: &'a TabooSet,
+    /// Whether captures are module names, matched only as a whole or by prefix entries.
+    modules: bool,
+    /// The captures needing special handling, present only for [`identifier_query`].
+    identifier_captures: Option<IdentifierCaptures>,
+}
+
+struct IdentifierCaptures {
+    /// Plain identifiers, which are reported without a label.
+    unlabelled: Vec<u32>,
+    quasiquote_body: u32,
+    pragma: u32,
+    template_haskell: u32,
+}
+
+impl<'a> ScanQuery<'a> {
+    pub fn identifiers(language: &Language, rules: &'a TabooSet, kinds: &[Kind]) -> Self {
+        let query =
+            Query::new(language, &identifier_query(kinds)).expect("Error constructing name query");
+        let capture = |name| {
+            query
+                .capture_index_for_name(name)
+                .expect("Error finding identifier query capture")
+        };
+        let identifier_captures = IdentifierCaptures {
+            unlabelled: ["variable-name", "operator-name"]
+                .into_iter()
+                .filter_map(|name| query.capture_index_for_name(name))
+                .collect(),
+            quasiquote_body: capture("quasiquote-body"),
+            pragma: capture("pragma"),
+            template_haskell: capture("template-haskell"),
+        };
+
+        ScanQuery {
+            query,
+            rules,
+            modules: false,
+            identifier_captures: Some(identifier_captures),
+        }
+    }
+
+    pub fn modules(language: &Language, rules: &'a TabooSet) -> Self {
+        ScanQuery {
+            query: Query::new(language, MODULE_QUERY).expect("Error constructing module query"),
+            rules,
+            modules: true,
+            identifier_captures: None,
+        }
+    }
+
+    pub fn section(language: &Language, section: &'a QuerySection) -> Result<Self, TabooError> {
+        let query = match &section.query {
+            SectionQuery::Query(query) => query,
+            SectionQuery::Kinds(kinds) => {
+                return Ok(ScanQuery::identifiers(language, &section.taboo_set, kinds));
+            }
+        };
+        let query = Query::new(language, query).map_err(|source| TabooError::InvalidQuery {
+            query: query.clone(),
+            source,
+        })?;
+
+        Ok(ScanQuery {
+            query,
+            rules: &section.taboo_set,
+            modules: false,
+            identifier_captures: None,
+        })
+    }
+}
+
+/// Compiles the queries that check the entries of `taboo_set`, whose entries outside of
+/// sections are checked against identifiers of each of `kinds`, or are module names if
+/// `ban_modules` is set.
+pub fn scan_queries<'a>(
+    language: &Language,
+    taboo_set: &'a TabooSet,
+    kinds: &[Kind],
+    ban_modules: bool,
+) -> Result<Vec<ScanQuery<'a>>, TabooError> {
+    let mut queries = vec![if ban_modules {
+        ScanQuery::modules(language, taboo_set)
+    } else {
+        ScanQuery::identifiers(language, taboo_set, kinds)
+    }];
+    for section in &taboo_set.sections {
+        queries.push(ScanQuery::section(language, section)?);
+    }
+    Ok(queries)
+}
+
+/// Collects the occurrences captured by `scan_query` in a parsed file.
+fn collect_occurrences<'a>(
+    scan_query: &'a ScanQuery,
+    tree: &Tree,
+    source: &'a [u8],
+    options: &ScanOptions,
+    occurrences: &mut Vec<Occurrence<'a>>,
+) -> Result<(), Utf8Error> {
+    let mut query_cursor = QueryCursor::new();
+    let mut matches = query_cursor.matches(&scan_query.query, tree.root_node(), source);
+    let mut template_haskell_ranges = Vec::new();
+    let first_occurrence = occurrences.len();
+
+    while let Some(query_match) = matches.next() {
+        for capture in query_match.captures {
+            if is_ignored(capture.node, &options.ignore_in) {
+                continue;
+            }
+            let text = capture.node.utf8_text(source)?;
+            let mut label = None;
+
+            if let Some(special) = &scan_query.identifier_captures {
+                if capture.index == special.template_haskell {
+                    template_haskell_ranges.push(capture.node.byte_range());
+                    continue;
+                }
+
+                let word_scan_label = if capture.index == special.quasiquote_body {
+                    Some("TH")
+                } else if capture.index == special.pragma {
+                    if !options.include_pragmas {
+                        continue;
+                    }
+                    Some("pragma")
+                } else {
+                    None
+                };
+                if !special.unlabelled.contains(&capture.index) {
+                    label = Some(scan_query.query.capture_names()[capture.index as usize]);
+                }
+
+                if let Some(label) = word_scan_label {
+                    if options.only == Some(Only::Def) {
+                        continue;
+                    }
+
+                    for (offset, word) in words(text, options.matching.prime_is_word_char) {
+                        occurrences.push(Occurrence {
+                            start_byte: capture.node.start_byte() + offset,
+                            end_byte: capture.node.start_byte() + offset + word.len(),
+                            position: position_within(capture.node.start_position(), text, offset),
+                            text: word,
+                            name: word,
+                            label: Some(label),
+                            identifier: false,
+                            rules: scan_query.rules,
+                        });
+                    }
+                    continue;
+                }
+            } else {
+                label = Some(scan_query.query.capture_names()[capture.index as usize]);
+            }
+
+            let wanted_site = match options.only {
+                Some(Only::Def) => is_definition(capture.node),
+                Some(Only::Use) => !is_definition(capture.node),
+                None => true,
+            };
+            if !wanted_site {
+                continue;
+            }
+
+            // with qualified matching, the occurrence spans the whole qualified name, which
+            // for an operator like `Map.!` is the `qualified` node around its `operator`
+            let node = match capture.node.parent().filter(|_| options.qualified) {
+                Some(parent)
+                    if parent.kind() == "qualified"
+                        && parent.child_by_field_name("id") == Some(capture.node) =>
+                {
+                    parent
+                }
+                _ => capture.node,
+            };
+
+            occurrences.push(Occurrence {
+                start_byte: node.start_byte(),
+                end_byte: node.end_byte(),
+                position: node.start_position(),
+                text: node.utf8_text(source)?,
+                name: text,
+                label,
+                identifier: IDENTIFIER_NODES.contains(&capture.node.kind()),
+                rules: scan_query.rules,
+            });
+        }
+    }
+
+    for occurrence in &mut occurrences[first_occurrence..] {
+        let in_template_haskell = template_haskell_ranges
+            .iter()
+            .any(|range| range.contains(&occurrence.start_byte));
+        if occurrence.label.is_none() && in_template_haskell {
+            occurrence.label = Some("TH");
+        }
+    }
+
+    // a node captured both by a labelled pattern and a plain one, like the operator of an
+    // infix pattern synonym, is only reported once, with its most specific label
+    let mut captured = occurrences.split_off(first_occurrence);
+    captured.sort_by_key(|o| {
+        let specificity = match o.label {
+            Some(label) if KIND_LABELS.contains(&label) => 1,
+            Some(_) => 0,
+            None => 2,
+        };
+        (o.start_byte, o.end_byte, specificity)
+    });
+    captured.dedup_by_key(|o| (o.start_byte, o.end_byte));
+    occurrences.extend(captured);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::taboo::banned_words_from;
+
+    /// The text of each violation of the entries of `list` in `source`, in order.
+    fn violations(list: &str, source: &str, options: ScanOptions) -> Vec<String> {
+        let taboo_set = banned_words_from(list.as_bytes()).unwrap();
+        let language = tree_sitter_haskell::LANGUAGE.into();
+        let queries = scan_queries(&language, &taboo_set, &Kind::DEFAULT, false).unwrap();
+        let scanner = Scanner::new(queries, options);
+        let path = Path::new("A.hs");
+        let found = scanner
+            .find(&mut new_parser(&language), source.as_bytes(), path)
+            .unwrap();
+        let mut violations = scanner
+            .scan(source.as_bytes(), path, &found)
+            .unwrap()
+            .violations;
+        violations.sort_by_key(|v| v.occurrence.start_byte);
+        violations
+            .iter()
+            .map(|v| v.occurrence.text.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn operators_are_matched_in_left_and_right_sections() {
+        let source = "a = (`div` 2)\nb = (2 `div`)\nc = (<+> x)\nd = (x <+>)\n";
+        assert_eq!(
+            violations("div\n<+>\n", source, ScanOptions::default()),
+            ["div", "div", "<+>", "<+>"]
+        );
+    }
+
+    #[test]
+    fn each_source_is_parsed_once_for_every_query() {
+        let list = "foo\n[query (string) @s]\n\"bar\"\n";
+        let taboo_set = banned_words_from(list.as_bytes()).unwrap();
+        let language = tree_sitter_haskell::LANGUAGE.into();
+        let queries = scan_queries(&language, &taboo_set, &Kind::DEFAULT, false).unwrap();
+        assert!(queries.len() > 1);
+        let scanner = Scanner::new(queries, ScanOptions::default());
+
+        let parses = std::rc::Rc::new(std::cell::Cell::new(0));
+        let mut parser = new_parser(&language);
+        let counted = parses.clone();
+        parser.set_logger(Some(Box::new(move |log_type, message| {
+            if log_type == tree_sitter::LogType::Parse && message == "new_parse" {
+                counted.set(counted.get() + 1);
+            }
+        })));
+        let source = b"f = foo \"bar\"\n";
+        let found = scanner
+            .find(&mut parser, source, Path::new("A.hs"))
+            .unwrap();
+        assert_eq!(found.occurrences.len(), 2);
+        assert_eq!(parses.get(), 1);
+    }
+
+    #[test]
+    fn functions_are_matched_in_view_patterns() {
+        let source = "f (unsafeFoo -> x) = x\n";
+        assert_eq!(
+            violations("unsafeFoo\n", source, ScanOptions::default()),
+            ["unsafeFoo"]
+        );
+    }
+
+    #[test]
+    fn names_are_matched_in_pattern_synonyms() {
+        let source = "pattern Foo x = Just x\n\
+                      pattern Bar{bar} = Just bar\n\
+                      pattern x :>: y = (x, y)\n";
+        assert_eq!(
+            violations("Foo\nBar\nbar\n:>:\n", source, ScanOptions::default()),
+            ["Foo", "Bar", "bar", "bar", ":>:"]
+        );
+    }
+
+    #[test]
+    fn qualified_operators_are_matched_with_qualified() {
+        let source = "a = m Map.! k\nb = (Map.!) m k\nc = m ! k\n";
+        let options = ScanOptions {
+            qualified: true,
+            ..ScanOptions::default()
+        };
+        assert_eq!(violations("Map.!\n", source, options), ["Map.!", "Map.!"]);
+    }
+
+    #[test]
+    fn do_binds_are_definitions() {
+        let source = "main = do\n  x <- foo\n  print x\n";
+        let only = |only| ScanOptions {
+            only: Some(only),
+            ..ScanOptions::default()
+        };
+        assert_eq!(violations("x\n", source, only(Only::Def)), ["x"]);
+        assert_eq!(violations("x\n", source, only(Only::Use)), ["x"]);
+        assert_eq!(
+            violations("x\n", source, ScanOptions::default()),
+            ["x", "x"]
+        );
+    }
+
+    #[test]
+    fn type_operators_are_matched_in_types() {
+        let source = "type T = Int :+: Bool\nf :: (f :+: g) a -> ()\n";
+        assert_eq!(
+            violations(":+:\n", source, ScanOptions::default()),
+            [":+:", ":+:"]
+        );
+    }
+}
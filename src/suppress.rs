@@ -0,0 +1,76 @@
+use crate::scanner::{Violation, line_around};
+use crate::taboo::{TabooMatch, TabooSet};
+use regex::Regex;
+use std::path::Path;
+use std::sync::LazyLock;
+
+/// A comment that permits every banned word on its line.
+pub const ALLOW_MARKER: &str = "-- taboo:allow";
+
+/// A comment like `-- taboo: ignore foo, bar` or `{- taboo: ignore foo -}`, capturing the
+/// names after `ignore`.
+static IGNORE_COMMENT: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?:--|\{-)\s*taboo:\s*ignore\b(.*?)(?:-\}|$)").expect("valid regex")
+});
+
+/// Whether `line` has an ignore comment suppressing `matched`.
+///
+/// The comment names the banned entries or alias groups it suppresses, separated by
+/// commas or spaces, and suppresses every entry if it names none. It applies to the
+/// line it's on and the line after it, so it can sit above the code it excuses.
+pub fn ignores(line: &[u8], matched: &TabooMatch) -> bool {
+    let line = String::from_utf8_lossy(line);
+    IGNORE_COMMENT.captures_iter(&line).any(|comment| {
+        let mut names = comment[1]
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|name| !name.is_empty())
+            .peekable();
+        names.peek().is_none()
+            || names.any(|name| name == matched.word || name == matched.canonical)
+    })
+}
+
+/// Whether `line` carries [`ALLOW_MARKER`].
+pub fn contains_marker(line: &[u8]) -> bool {
+    line.windows(ALLOW_MARKER.len())
+        .any(|window| window == ALLOW_MARKER.as_bytes())
+}
+
+/// Why a violation written in a source isn't reported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Suppression {
+    /// An [`ALLOW_MARKER`] on its line.
+    Marker,
+    /// An ignore comment on its line or the line before.
+    Comment,
+    /// An `[allow]` entry of the taboo file for the file or its module.
+    Allowance,
+}
+
+/// What suppresses `violation` in `source`, the contents of the file at `path`
+/// declaring `module`, if anything, checking the `[allow]` entries of `taboo_set`.
+pub fn suppression(
+    violation: &Violation,
+    path: &Path,
+    source: &[u8],
+    module: Option<&str>,
+    taboo_set: &TabooSet,
+) -> Option<Suppression> {
+    let occurrence = &violation.occurrence;
+    let line = line_around(source, occurrence.start_byte, occurrence.end_byte);
+    let previous_line = line
+        .start
+        .checked_sub(1)
+        .map(|end| line_around(source, end, end));
+    if contains_marker(&source[line.clone()]) {
+        Some(Suppression::Marker)
+    } else if ignores(&source[line], &violation.matched)
+        || previous_line.is_some_and(|previous| ignores(&source[previous], &violation.matched))
+    {
+        Some(Suppression::Comment)
+    } else if taboo_set.allows(&violation.matched, path, module) {
+        Some(Suppression::Allowance)
+    } else {
+        None
+    }
+}
@@ -0,0 +1,1243 @@
+use crate::TabooError;
+use crate::pattern::PatternSet;
+use crate::scanner::Kind;
+use crate::trie::PrefixTrie;
+use clap::ValueEnum;
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use regex::Regex;
+use serde::Serialize;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+use std::sync::OnceLock;
+use tree_sitter::Query;
+
+/// The entries of a banned words list.
+///
+/// Lines starting with `!` are exceptions rather than bans. Exceptions always take
+/// precedence: an identifier that is exactly an exception entry is never reported,
+/// even if it is banned outright or contains a banned word.
+///
+/// An entry ending in `*` after an alphanumeric name, like `internal*` or `!unsafeSafe*`,
+/// matches every identifier starting with that name. Other entries with `*` or `?` in an
+/// otherwise alphanumeric name, like `*Impl` or `get?`, are globs matching whole
+/// identifiers, where `*` matches any run of characters and `?` any one. An entry
+/// starting with `re:`, like `re:^unsafe[A-Z]`, is a regular expression matching any
+/// identifier it is found in. Symbolic entries like `<*` are operators and are always
+/// matched literally.
+///
+/// An entry can be followed by `::` and comma-separated annotations. `foo :: warning`
+/// reports `foo` as a warning rather than an error, which only fails a scan beyond
+/// `--max-warnings`. `error :: in src/Core/**` bans `error` only in files whose path
+/// matches the glob, where `*` stays within a directory and `**` spans any number of
+/// them; an entry with several `in` annotations applies to files matching any of them.
+/// Listing an entry twice in the same section is an error, even with different
+/// annotations, since the later line would replace the earlier one.
+/// `password :: arg-of log` only reports `password` within an argument of an application
+/// of `log`, like `log password`, `log (show password)` or `log $ password`; an entry
+/// with several `arg-of` annotations is reported in the arguments of any of them.
+/// `tempVar :: min 3` only reports `tempVar` once it has at least 3 violations, so that
+/// a few legitimate uses pass while copy-pasted proliferation doesn't.
+///
+/// A literal entry can name its replacement after `=>`, like `whitelist => allowlist`
+/// or `whitelist => allowlist :: warning`, which `--fix` rewrites its violations to.
+///
+/// A line like `Config: cfg, conf, cnf` bans each of the comma-separated aliases after
+/// the colon while grouping their violations under the name before it in grouped
+/// reports, JSON output and `min` counts. Annotations after the aliases apply to all
+/// of them.
+///
+/// A `[query <query>]` line starts a section whose entries are only checked against
+/// the captures of that tree-sitter query, rather than the identifiers checked by
+/// default. A `[kinds <kind>, ...]` line starts a section whose entries are checked
+/// against identifiers of only those kinds, like `[kinds type, class]`, regardless of
+/// `--kinds`. A section runs until the next section or the end of the list.
+///
+/// An `[allow]` line starts a section of entries whose violations are never reported,
+/// whichever section banned them, like `show :: in src/Pretty/**` or
+/// `unsafePerformIO :: module Data.Cache`. `in` limits an allowance to files matching
+/// the glob and `module` to files declaring the module, where `Data.Cache.*` also covers
+/// every module beneath `Data.Cache`; an allowance without either applies everywhere.
+/// Allowances name banned entries, or alias groups, rather than identifiers.
+#[derive(Debug, Default, Clone)]
+pub struct TabooSet {
+    banned: HashMap<String, Ban>,
+    banned_prefixes: PrefixTrie<Ban>,
+    exceptions: HashSet<String>,
+    exception_prefixes: PrefixTrie<String>,
+    banned_patterns: PatternSet<Ban>,
+    exception_patterns: PatternSet<String>,
+    /// Every entry compiled for [`MatchOptions::ignore_case`], the first time it's needed.
+    ignoring_case: OnceLock<CaseInsensitive>,
+    pub sections: Vec<QuerySection>,
+    pub allowances: Vec<Allowance>,
+}
+
+/// An entry of the `[allow]` section, suppressing the violations of a banned entry.
+#[derive(Debug, Clone)]
+pub struct Allowance {
+    /// The banned entry or alias group allowed.
+    pub word: String,
+    /// The files the allowance is limited to, if any.
+    pub paths: Option<GlobSet>,
+    /// The modules the allowance is limited to, if any.
+    pub modules: Vec<String>,
+}
+
+impl Allowance {
+    fn allows(&self, matched: &TabooMatch, path: &Path, module: Option<&str>) -> bool {
+        if self.word != matched.word && self.word != matched.canonical {
+            return false;
+        }
+        let in_paths = match &self.paths {
+            Some(paths) => paths.is_match(path.strip_prefix(".").unwrap_or(path)),
+            None => true,
+        };
+        let in_modules = self.modules.is_empty()
+            || module.is_some_and(|module| {
+                self.modules
+                    .iter()
+                    .any(|allowed| match allowed.strip_suffix(".*") {
+                        Some(parent) => {
+                            module == parent
+                                || module
+                                    .strip_prefix(parent)
+                                    .is_some_and(|rest| rest.starts_with('.'))
+                        }
+                        None => module == allowed,
+                    })
+            });
+        in_paths && in_modules
+    }
+}
+
+/// The entries of a [`TabooSet`] as patterns matching regardless of case, literal and
+/// prefix entries included, in the order they take precedence.
+#[derive(Debug, Clone)]
+struct CaseInsensitive {
+    bans: PatternSet<(Ban, EntryKind)>,
+    exceptions: PatternSet<String>,
+}
+
+/// Entries checked only against the captures of their own query.
+#[derive(Debug, Clone)]
+pub struct QuerySection {
+    pub query: SectionQuery,
+    pub taboo_set: TabooSet,
+}
+
+/// What the entries of a section are checked against.
+#[derive(Debug, Clone)]
+pub enum SectionQuery {
+    /// The captures of a tree-sitter query.
+    Query(String),
+    /// Identifiers of these kinds, as with `--kinds`.
+    Kinds(Vec<Kind>),
+}
+
+impl fmt::Display for SectionQuery {
+    /// Formats the section's header as written in the list.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SectionQuery::Query(query) => write!(f, "[query {}]", query),
+            SectionQuery::Kinds(kinds) => {
+                let kinds: Vec<String> = kinds
+                    .iter()
+                    .filter_map(|kind| kind.to_possible_value())
+                    .map(|value| value.get_name().to_string())
+                    .collect();
+                write!(f, "[kinds {}]", kinds.join(", "))
+            }
+        }
+    }
+}
+
+impl TabooSet {
+    pub fn insert(&mut self, entry: TabooEntry) {
+        self.ignoring_case = OnceLock::new();
+        if let Some(pattern) = pattern_of(entry.word()) {
+            match entry {
+                TabooEntry::Ban(ban) => self.banned_patterns.insert(pattern, ban),
+                TabooEntry::Exception(word) => self.exception_patterns.insert(pattern, word),
+            }
+            return;
+        }
+
+        match entry {
+            TabooEntry::Ban(ban) => match prefix_of(&ban.word) {
+                Some(prefix) => {
+                    let prefix = prefix.to_owned();
+                    self.banned_prefixes.insert(&prefix, ban)
+                }
+                None => {
+                    self.banned.insert(ban.word.clone(), ban);
+                }
+            },
+            TabooEntry::Exception(word) => match prefix_of(&word) {
+                Some(prefix) => {
+                    let prefix = prefix.to_owned();
+                    self.exception_prefixes.insert(&prefix, word)
+                }
+                None => {
+                    self.exceptions.insert(word);
+                }
+            },
+        };
+    }
+
+    /// Returns the entry applying to `path` that `text` matches in full, literally or
+    /// by prefix, or that matches it as a pattern.
+    fn entry_for(
+        &self,
+        text: &str,
+        path: &Path,
+        options: MatchOptions,
+    ) -> Option<(&Ban, EntryKind)> {
+        if options.ignore_case {
+            return self
+                .ignoring_case()
+                .bans
+                .matching(text)
+                .find(|(ban, _)| ban.applies_to(path))
+                .map(|(ban, kind)| (ban, *kind));
+        }
+
+        if let Some(ban) = self.banned.get(text).filter(|ban| ban.applies_to(path)) {
+            return Some((ban, EntryKind::Literal));
+        }
+        if let Some(ban) = self
+            .banned_prefixes
+            .matching(text)
+            .find(|ban| ban.applies_to(path))
+        {
+            return Some((ban, EntryKind::Prefix));
+        }
+
+        self.banned_patterns
+            .matching(text)
+            .find(|ban| ban.applies_to(path))
+            .map(|ban| (ban, EntryKind::Pattern))
+    }
+
+    fn ignoring_case(&self) -> &CaseInsensitive {
+        self.ignoring_case.get_or_init(|| {
+            let mut literals: Vec<&Ban> = self.banned.values().collect();
+            literals.sort_by(|a, b| a.word.cmp(&b.word));
+            let mut prefixes: Vec<&Ban> = self.banned_prefixes.values().collect();
+            prefixes.sort_by(|a, b| a.word.cmp(&b.word));
+
+            let mut bans = PatternSet::new(true);
+            for ban in literals {
+                bans.insert(
+                    literal_pattern(&ban.word),
+                    (ban.clone(), EntryKind::Literal),
+                );
+            }
+            for ban in prefixes {
+                bans.insert(prefix_pattern(&ban.word), (ban.clone(), EntryKind::Prefix));
+            }
+            for ban in self.banned_patterns.values() {
+                let pattern = pattern_of(&ban.word).expect("only patterns are inserted");
+                bans.insert(pattern, (ban.clone(), EntryKind::Pattern));
+            }
+
+            let mut literals: Vec<&String> = self.exceptions.iter().collect();
+            literals.sort();
+            let mut prefixes: Vec<&String> = self.exception_prefixes.values().collect();
+            prefixes.sort();
+
+            let mut exceptions = PatternSet::new(true);
+            for word in literals {
+                exceptions.insert(literal_pattern(word), word.clone());
+            }
+            for word in prefixes {
+                exceptions.insert(prefix_pattern(word), word.clone());
+            }
+            for word in self.exception_patterns.values() {
+                let pattern = pattern_of(word).expect("only patterns are inserted");
+                exceptions.insert(pattern, word.clone());
+            }
+
+            CaseInsensitive { bans, exceptions }
+        })
+    }
+
+    /// Like [`TabooSet::entry_for`], but with [`MatchOptions::fold_leading_case`] also
+    /// tries `text` with the case of its first character swapped.
+    fn entry_folding(
+        &self,
+        text: &str,
+        path: &Path,
+        options: MatchOptions,
+    ) -> Option<(&Ban, EntryKind)> {
+        self.entry_for(text, path, options).or_else(|| {
+            let folded = swap_leading_case(text).filter(|_| options.fold_leading_case)?;
+            self.entry_for(&folded, path, options)
+        })
+    }
+
+    /// Returns the ban that `text` in the file at `path` matches, either as a whole, as
+    /// one of its words or, with [`MatchOptions::substring`], anywhere within it, without
+    /// considering exceptions.
+    ///
+    /// With [`MatchOptions::ignore_case`], substrings are compared ignoring only ASCII
+    /// case, so that their offsets stay those of `text`.
+    pub fn ban_for(
+        &self,
+        text: &str,
+        path: &Path,
+        options: MatchOptions,
+    ) -> Option<TabooMatch<'_>> {
+        if let Some((ban, kind)) = self.entry_folding(text, path, options) {
+            return Some(TabooMatch::new(ban, kind, MatchRule::Entry));
+        }
+
+        let prime_is_word_char = options.prime_is_word_char;
+        let word_match = words(text, prime_is_word_char).find_map(|(_, w)| {
+            self.entry_folding(w, path, options).map(|(ban, kind)| {
+                TabooMatch::new(ban, kind, MatchRule::Word { prime_is_word_char })
+            })
+        });
+        if word_match.is_some() || !options.substring {
+            return word_match;
+        }
+
+        // the earliest match, so that the same entry is reported for the same text
+        let haystack = if options.ignore_case {
+            Cow::Owned(text.to_ascii_lowercase())
+        } else {
+            Cow::Borrowed(text)
+        };
+        self.banned
+            .values()
+            .filter(|ban| ban.applies_to(path))
+            .filter_map(|ban| {
+                let offset = if options.ignore_case {
+                    haystack.find(&ban.word.to_ascii_lowercase())
+                } else {
+                    haystack.find(ban.word.as_str())
+                };
+                offset.map(|offset| (offset, ban))
+            })
+            .min_by_key(|(offset, ban)| (*offset, ban.word.as_str()))
+            .map(|(offset, ban)| {
+                TabooMatch::new(ban, EntryKind::Literal, MatchRule::Substring { offset })
+            })
+    }
+
+    /// Like [`TabooSet::ban_for`], but also matches entries naming `qualified`, the full
+    /// qualified form of `name`.
+    pub fn ban_for_qualified(
+        &self,
+        qualified: &str,
+        name: &str,
+        path: &Path,
+        options: MatchOptions,
+    ) -> Option<TabooMatch<'_>> {
+        if let Some((ban, kind)) = self.entry_folding(qualified, path, options) {
+            return Some(TabooMatch::new(ban, kind, MatchRule::Entry));
+        }
+
+        self.ban_for(name, path, options)
+    }
+
+    /// Returns the ban on importing `module` in the file at `path`, matching only whole
+    /// module names or prefix entries like `Database.*`, without considering exceptions.
+    pub fn ban_for_module(
+        &self,
+        module: &str,
+        path: &Path,
+        options: MatchOptions,
+    ) -> Option<TabooMatch<'_>> {
+        self.entry_for(module, path, options)
+            .map(|(ban, kind)| TabooMatch::new(ban, kind, MatchRule::Entry))
+    }
+
+    /// Returns every ban outside of any section, in no particular order.
+    pub fn bans(&self) -> impl Iterator<Item = &Ban> {
+        self.banned
+            .values()
+            .chain(self.banned_prefixes.values())
+            .chain(self.banned_patterns.values())
+    }
+
+    /// Returns every banned entry, including those in sections, in no particular order.
+    pub fn banned_entries(&self) -> impl Iterator<Item = &str> {
+        self.bans()
+            .chain(self.sections.iter().flat_map(|s| s.taboo_set.bans()))
+            .map(|ban| ban.word.as_str())
+    }
+
+    /// Whether any entry, including those in sections, has a `:: min` annotation.
+    pub fn has_min_occurrences(&self) -> bool {
+        self.bans().any(|ban| ban.min_occurrences.is_some())
+            || self
+                .sections
+                .iter()
+                .any(|section| section.taboo_set.has_min_occurrences())
+    }
+
+    /// Adds every entry and section of `other` to this set.
+    pub fn extend(&mut self, other: &TabooSet) {
+        for ban in other.bans() {
+            self.insert(TabooEntry::Ban(ban.clone()));
+        }
+        for exception in other
+            .exceptions
+            .iter()
+            .chain(other.exception_prefixes.values())
+            .chain(other.exception_patterns.values())
+        {
+            self.insert(TabooEntry::Exception(exception.clone()));
+        }
+        self.sections.extend(other.sections.iter().cloned());
+        self.allowances.extend(other.allowances.iter().cloned());
+    }
+
+    /// Whether an `[allow]` entry suppresses `matched` in the file at `path`, which
+    /// declares `module` if it has a header.
+    pub fn allows(&self, matched: &TabooMatch, path: &Path, module: Option<&str>) -> bool {
+        self.allowances
+            .iter()
+            .any(|allowance| allowance.allows(matched, path, module))
+    }
+
+    /// Returns the exception entry permitting `text`, if there is one, folding the case
+    /// of its first character like [`TabooSet::ban_for`] does with `options`.
+    pub fn exception_for(&self, text: &str, options: MatchOptions) -> Option<&str> {
+        if options.ignore_case {
+            return self
+                .ignoring_case()
+                .exceptions
+                .matching(text)
+                .next()
+                .map(String::as_str);
+        }
+
+        let exact = |text: &str| {
+            self.exceptions
+                .get(text)
+                .or_else(|| self.exception_prefixes.matching(text).next())
+                .or_else(|| self.exception_patterns.matching(text).next())
+                .map(String::as_str)
+        };
+        exact(text).or_else(|| {
+            let folded = swap_leading_case(text).filter(|_| options.fold_leading_case)?;
+            exact(&folded)
+        })
+    }
+}
+
+impl TabooSet {
+    fn describe_counts(&self) -> String {
+        let prefixes = self.banned_prefixes.values().count();
+        let patterns = self.banned_patterns.values().count();
+        let warnings = self
+            .bans()
+            .filter(|ban| ban.severity == Severity::Warning)
+            .count();
+        let exception_prefixes = self.exception_prefixes.values().count();
+        let exception_patterns = self.exception_patterns.values().count();
+        format!(
+            "{} entries ({} prefixes, {} patterns, {} warnings), {} exceptions ({} prefixes, {} patterns)",
+            self.banned.len() + prefixes + patterns,
+            prefixes,
+            patterns,
+            warnings,
+            self.exceptions.len() + exception_prefixes + exception_patterns,
+            exception_prefixes,
+            exception_patterns
+        )
+    }
+}
+
+/// Returns the name an entry like `internal*` matches the prefix of, if it is one.
+fn prefix_of(entry: &str) -> Option<&str> {
+    entry.strip_suffix('*').filter(|prefix| {
+        prefix.starts_with(|c: char| c.is_alphabetic() || c == '_') && !prefix.contains(['*', '?'])
+    })
+}
+
+/// Returns the regular expression that an entry like `re:^unsafe` or the glob `*Impl`
+/// matches identifiers with, if it is one.
+fn pattern_of(entry: &str) -> Option<String> {
+    if let Some(regex) = entry.strip_prefix("re:") {
+        return Some(regex.to_string());
+    }
+
+    let is_name_char = |c: char| c.is_alphanumeric() || c == '_' || c == '\'' || c == '.';
+    let is_glob = prefix_of(entry).is_none()
+        && entry.contains(['*', '?'])
+        && entry.chars().any(is_name_char)
+        && entry
+            .chars()
+            .all(|c| is_name_char(c) || c == '*' || c == '?');
+    if !is_glob {
+        return None;
+    }
+
+    let mut regex = String::from("^");
+    for c in entry.chars() {
+        match c {
+            '*' => regex.push_str(".*"),
+            '?' => regex.push('.'),
+            c => regex.push_str(&regex::escape(c.encode_utf8(&mut [0; 4]))),
+        }
+    }
+    regex.push('$');
+    Some(regex)
+}
+
+/// The pattern matching a literal entry, for [`MatchOptions::ignore_case`].
+fn literal_pattern(word: &str) -> String {
+    format!("^{}$", regex::escape(word))
+}
+
+/// The pattern matching a prefix entry like `internal*`, for [`MatchOptions::ignore_case`].
+fn prefix_pattern(word: &str) -> String {
+    format!("^{}", regex::escape(prefix_of(word).unwrap_or(word)))
+}
+
+/// Returns `text` with its first character in the other case, if it has one.
+pub fn swap_leading_case(text: &str) -> Option<String> {
+    let mut chars = text.chars();
+    let first = chars.next()?;
+    let swapped: String = if first.is_uppercase() {
+        first.to_lowercase().collect()
+    } else if first.is_lowercase() {
+        first.to_uppercase().collect()
+    } else {
+        return None;
+    };
+    Some(swapped + chars.as_str())
+}
+
+/// A ban that an identifier matched, and how it matched.
+#[derive(Debug, Clone, Copy)]
+pub struct TabooMatch<'a> {
+    pub word: &'a str,
+    /// The name of the entry's alias group, or the entry itself outside of one.
+    pub canonical: &'a str,
+    pub severity: Severity,
+    pub min_occurrences: Option<usize>,
+    pub arg_of: &'a [String],
+    pub replacement: Option<&'a str>,
+    pub kind: EntryKind,
+    pub rule: MatchRule,
+}
+
+/// A banned entry as written in the list, and how it is enforced.
+#[derive(Debug, Clone)]
+pub struct Ban {
+    pub word: String,
+    /// The name of the alias group the entry was listed in, if any.
+    pub group: Option<String>,
+    pub severity: Severity,
+    /// The files the ban is limited to, if it isn't banned everywhere.
+    pub paths: Option<GlobSet>,
+    /// The fewest violations of the entry that are reported, if not just one.
+    pub min_occurrences: Option<usize>,
+    /// The functions the entry is only banned in the arguments of, if not everywhere.
+    pub arg_of: Vec<String>,
+    /// What `--fix` replaces the entry with, if anything.
+    pub replacement: Option<String>,
+}
+
+impl Ban {
+    fn applies_to(&self, path: &Path) -> bool {
+        match &self.paths {
+            // `./src/A.hs` should match `src/**` like `src/A.hs` does
+            Some(paths) => paths.is_match(path.strip_prefix(".").unwrap_or(path)),
+            None => true,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Default, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Severity {
+    #[default]
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum EntryKind {
+    Literal,
+    Prefix,
+    /// A glob or regular expression.
+    Pattern,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum MatchRule {
+    /// The identifier is exactly the banned entry.
+    Entry,
+    /// The banned entry is one of the words the identifier splits into.
+    Word { prime_is_word_char: bool },
+    /// The banned entry appears `offset` bytes into the identifier.
+    Substring { offset: usize },
+}
+
+/// How identifiers are compared against entries.
+#[derive(Debug, Clone, Copy)]
+pub struct MatchOptions {
+    /// Whether apostrophes are part of words, so that `foo'` is a word of its own
+    /// rather than `foo` primed, as Haskell reads it.
+    pub prime_is_word_char: bool,
+    /// Whether literal entries also match anywhere within an identifier.
+    pub substring: bool,
+    /// Whether the case of an identifier's first character is ignored, so that `Foo`
+    /// matches `foo`.
+    pub fold_leading_case: bool,
+    /// Whether case is ignored entirely, so that `foo` matches `FOO` and `fOo`.
+    pub ignore_case: bool,
+}
+
+impl Default for MatchOptions {
+    fn default() -> Self {
+        MatchOptions {
+            prime_is_word_char: true,
+            substring: false,
+            fold_leading_case: false,
+            ignore_case: false,
+        }
+    }
+}
+
+impl<'a> TabooMatch<'a> {
+    fn new(ban: &'a Ban, kind: EntryKind, rule: MatchRule) -> Self {
+        TabooMatch {
+            word: &ban.word,
+            canonical: ban.group.as_deref().unwrap_or(&ban.word),
+            severity: ban.severity,
+            min_occurrences: ban.min_occurrences,
+            arg_of: &ban.arg_of,
+            replacement: ban.replacement.as_deref(),
+            kind,
+            rule,
+        }
+    }
+
+    /// Describes why `text` matched, for `--explain`.
+    pub fn explain(&self, text: &str) -> String {
+        let kind = match self.kind {
+            EntryKind::Literal => "literal",
+            EntryKind::Prefix => "prefix",
+            EntryKind::Pattern => "pattern",
+        };
+
+        let explanation = match self.rule {
+            MatchRule::Entry => format!("{} entry `{}`", kind, self.word),
+            MatchRule::Word { prime_is_word_char } => format!(
+                "{} entry `{}` as a word of `{}`{}",
+                kind,
+                self.word,
+                text,
+                if prime_is_word_char {
+                    ""
+                } else {
+                    " (apostrophes split words)"
+                }
+            ),
+            MatchRule::Substring { .. } => {
+                format!(
+                    "{} entry `{}` as a substring of `{}`",
+                    kind, self.word, text
+                )
+            }
+        };
+        match self.replacement {
+            Some(replacement) => format!("{explanation}, to be replaced with `{replacement}`"),
+            None => explanation,
+        }
+    }
+}
+
+/// A single line of a banned words list.
+#[derive(Debug, Clone)]
+pub enum TabooEntry {
+    Ban(Ban),
+    Exception(String),
+}
+
+impl TabooEntry {
+    fn word(&self) -> &str {
+        match self {
+            TabooEntry::Ban(ban) => &ban.word,
+            TabooEntry::Exception(word) => word,
+        }
+    }
+}
+
+/// Loads the banned words list at `path`.
+pub fn load_taboo_file(path: &Path) -> Result<TabooSet, TabooError> {
+    File::open(path)
+        .and_then(|file| banned_words_from(&file))
+        .map_err(|source| TabooError::TabooFile {
+            path: path.to_path_buf(),
+            source,
+        })
+}
+
+/// Reads a banned words list, written as for [`load_taboo_file`], from `reader`.
+pub fn banned_words_from(reader: impl Read) -> std::io::Result<TabooSet> {
+    taboo_set_from(reader, true)
+}
+
+fn taboo_set_from(reader: impl Read, reject_duplicates: bool) -> std::io::Result<TabooSet> {
+    let buf_read = BufReader::new(reader);
+    let mut taboo_set = TabooSet::default();
+    let mut first_seen: HashMap<(usize, bool, String), usize> = HashMap::new();
+    let mut in_allow = false;
+    for (index, line) in buf_read.lines().enumerate() {
+        let line = line?;
+        if in_allow && let Some((allowance, _)) = allowance_line(&line) {
+            taboo_set.allowances.push(allowance);
+            continue;
+        }
+        let Some(line) = taboo_line(&line) else {
+            continue;
+        };
+        in_allow = matches!(line, TabooLine::Allow);
+        match line {
+            TabooLine::Entry(entries, _) => {
+                let section = taboo_set.sections.len();
+                let set = match taboo_set.sections.last_mut() {
+                    Some(section) => &mut section.taboo_set,
+                    None => &mut taboo_set,
+                };
+                for entry in entries {
+                    let key = (
+                        section,
+                        matches!(entry, TabooEntry::Exception(_)),
+                        entry.word().to_string(),
+                    );
+                    if reject_duplicates && let Some(first) = first_seen.insert(key, index + 1) {
+                        return Err(std::io::Error::new(
+                            std::io::ErrorKind::InvalidData,
+                            format!(
+                                "line {}: duplicate entry `{}` (first seen on line {first})",
+                                index + 1,
+                                entry.word()
+                            ),
+                        ));
+                    }
+                    set.insert(entry);
+                }
+            }
+            TabooLine::Section(query, _) => taboo_set.sections.push(QuerySection {
+                query,
+                taboo_set: TabooSet::default(),
+            }),
+            TabooLine::Allow => {}
+        }
+    }
+    Ok(taboo_set)
+}
+
+/// A meaningful line of a banned words list.
+enum TabooLine {
+    /// Its entries, several for an alias group, along with descriptions of any problems
+    /// with their annotations.
+    Entry(Vec<TabooEntry>, Vec<String>),
+    /// A section header, along with descriptions of any problems with its kinds.
+    Section(SectionQuery, Vec<String>),
+    /// The `[allow]` header, whose entries are read by [`allowance_line`].
+    Allow,
+}
+
+/// Returns what a line of a banned words list describes, if anything.
+fn taboo_line(line: &str) -> Option<TabooLine> {
+    let entry = line.trim();
+    if entry.is_empty() {
+        return None;
+    }
+
+    if entry == "[allow]" {
+        return Some(TabooLine::Allow);
+    }
+
+    if let Some(query) = entry
+        .strip_prefix("[query ")
+        .and_then(|q| q.strip_suffix(']'))
+    {
+        return Some(TabooLine::Section(
+            SectionQuery::Query(query.trim().to_string()),
+            Vec::new(),
+        ));
+    }
+
+    if let Some(kinds) = entry
+        .strip_prefix("[kinds ")
+        .and_then(|k| k.strip_suffix(']'))
+    {
+        let mut section_kinds = Vec::new();
+        let mut problems = Vec::new();
+        for kind in kinds.split(',').map(str::trim).filter(|k| !k.is_empty()) {
+            match Kind::from_str(kind, false) {
+                Ok(kind) => section_kinds.push(kind),
+                Err(_) => problems.push(format!("unknown kind `{}`", kind)),
+            }
+        }
+        return Some(TabooLine::Section(
+            SectionQuery::Kinds(section_kinds),
+            problems,
+        ));
+    }
+
+    let (entry, annotations) = match entry.split_once(" :: ") {
+        Some((entry, annotations)) => (entry.trim_end(), annotations.split(',').collect()),
+        None => (entry, Vec::new()),
+    };
+    let (entry, replacement) = match entry.split_once(" => ") {
+        Some((entry, replacement)) => (entry.trim_end(), Some(replacement.trim())),
+        None => (entry, None),
+    };
+
+    if let Some(exception) = entry.strip_prefix('!') {
+        // exceptions have nothing to annotate or replace
+        let mut problems: Vec<String> = annotations
+            .iter()
+            .map(|a| format!("unknown annotation `{}`", a.trim()))
+            .collect();
+        if let Some(replacement) = replacement {
+            problems.push(format!(
+                "exceptions have no replacement, ignoring `{replacement}`"
+            ));
+        }
+        let exception = exception.trim();
+        let entries = match invalid_pattern(exception) {
+            Some(problem) => {
+                problems.push(problem);
+                Vec::new()
+            }
+            None => vec![TabooEntry::Exception(exception.to_string())],
+        };
+        return Some(TabooLine::Entry(entries, problems));
+    }
+
+    // the colon of a regular expression starts no alias group
+    let (group, aliases) = match alias_group(entry).filter(|_| !entry.starts_with("re:")) {
+        Some((group, aliases)) => (Some(group.to_string()), aliases),
+        None => (None, vec![entry]),
+    };
+    let mut ban = Ban {
+        word: String::new(),
+        group,
+        severity: Severity::default(),
+        paths: None,
+        min_occurrences: None,
+        arg_of: Vec::new(),
+        replacement: None,
+    };
+    let mut paths = GlobSetBuilder::new();
+    let mut scoped = false;
+    let mut problems = Vec::new();
+    match replacement {
+        Some("") => problems.push("empty replacement".to_string()),
+        Some(replacement) if !is_valid_identifier(replacement) => problems.push(format!(
+            "replacement `{replacement}` isn't an identifier or operator"
+        )),
+        Some(replacement) => ban.replacement = Some(replacement.to_string()),
+        None => {}
+    }
+    for annotation in annotations {
+        match annotation.trim() {
+            "error" => ban.severity = Severity::Error,
+            "warning" => ban.severity = Severity::Warning,
+            other => {
+                if let Some(glob) = other.strip_prefix("in ") {
+                    match GlobBuilder::new(glob.trim())
+                        .literal_separator(true)
+                        .build()
+                    {
+                        Ok(glob) => {
+                            paths.add(glob);
+                            scoped = true;
+                        }
+                        Err(e) => {
+                            problems.push(format!("invalid path glob `{}`: {}", glob.trim(), e))
+                        }
+                    }
+                } else if let Some(count) = other.strip_prefix("min ") {
+                    match count.trim().parse() {
+                        Ok(count) => ban.min_occurrences = Some(count),
+                        Err(_) => problems.push(format!(
+                            "invalid occurrence count `{}`, expected a number",
+                            count.trim()
+                        )),
+                    }
+                } else if let Some(function) = other.strip_prefix("arg-of ") {
+                    ban.arg_of.push(function.trim().to_string());
+                } else {
+                    problems.push(format!("unknown annotation `{other}`"));
+                }
+            }
+        }
+    }
+    if scoped {
+        match paths.build() {
+            Ok(paths) => ban.paths = Some(paths),
+            Err(e) => problems.push(format!("invalid path globs: {e}")),
+        }
+    }
+
+    let mut entries = Vec::new();
+    for alias in aliases {
+        if let Some(problem) = invalid_pattern(alias) {
+            problems.push(problem);
+            continue;
+        }
+        let mut alias_ban = Ban {
+            word: alias.to_string(),
+            ..ban.clone()
+        };
+        // a prefix or pattern has no single spelling to replace
+        if alias_ban.replacement.is_some()
+            && (prefix_of(alias).is_some() || pattern_of(alias).is_some())
+        {
+            problems.push(format!(
+                "only literal entries can be replaced, not `{alias}`"
+            ));
+            alias_ban.replacement = None;
+        }
+        entries.push(TabooEntry::Ban(alias_ban));
+    }
+    Some(TabooLine::Entry(entries, problems))
+}
+
+/// Returns the allowance a line of the `[allow]` section describes, if it isn't blank
+/// or a section header, along with descriptions of any problems with its annotations.
+fn allowance_line(line: &str) -> Option<(Allowance, Vec<String>)> {
+    let entry = line.trim();
+    if entry.is_empty() || entry.starts_with('[') {
+        return None;
+    }
+
+    let (word, annotations) = match entry.split_once(" :: ") {
+        Some((word, annotations)) => (word.trim_end(), annotations.split(',').collect()),
+        None => (entry, Vec::new()),
+    };
+    let mut allowance = Allowance {
+        word: word.to_string(),
+        paths: None,
+        modules: Vec::new(),
+    };
+    let mut paths = GlobSetBuilder::new();
+    let mut scoped = false;
+    let mut problems = Vec::new();
+    for annotation in annotations {
+        let annotation = annotation.trim();
+        if let Some(glob) = annotation.strip_prefix("in ") {
+            match GlobBuilder::new(glob.trim())
+                .literal_separator(true)
+                .build()
+            {
+                Ok(glob) => {
+                    paths.add(glob);
+                    scoped = true;
+                }
+                Err(e) => problems.push(format!("invalid path glob `{}`: {}", glob.trim(), e)),
+            }
+        } else if let Some(module) = annotation.strip_prefix("module ") {
+            allowance.modules.push(module.trim().to_string());
+        } else {
+            problems.push(format!("unknown annotation `{annotation}`"));
+        }
+    }
+    if scoped {
+        match paths.build() {
+            Ok(paths) => allowance.paths = Some(paths),
+            Err(e) => problems.push(format!("invalid path globs: {e}")),
+        }
+    }
+    Some((allowance, problems))
+}
+
+/// Describes why `entry` is a pattern that can't be compiled, if it is one, which drops
+/// the entry.
+fn invalid_pattern(entry: &str) -> Option<String> {
+    let pattern = pattern_of(entry)?;
+    let error = Regex::new(&pattern).err()?;
+    Some(format!("invalid pattern `{}`: {}", entry, error))
+}
+
+/// Splits an alias group like `Config: cfg, conf` into its name and aliases.
+///
+/// The name must be an identifier followed by whitespace after the colon, so that
+/// operator entries containing `:`, like `:|` or the qualified `NE.:|`, are never
+/// mistaken for groups.
+fn alias_group(entry: &str) -> Option<(&str, Vec<&str>)> {
+    let (group, aliases) = entry.split_once(':')?;
+    let is_name = group.starts_with(|c: char| c.is_alphabetic() || c == '_')
+        && !group.ends_with('.')
+        && group
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '_' || c == '\'' || c == '.');
+    if !is_name || !aliases.starts_with(char::is_whitespace) {
+        return None;
+    }
+
+    let aliases: Vec<&str> = aliases
+        .split(',')
+        .map(str::trim)
+        .filter(|alias| !alias.is_empty())
+        .collect();
+    (!aliases.is_empty()).then_some((group, aliases))
+}
+
+fn is_word_char(c: char, prime_is_word_char: bool) -> bool {
+    c.is_alphanumeric() || c == '_' || (prime_is_word_char && c == '\'')
+}
+
+/// Splits `text` into maximal runs of word characters, yielding each with its byte offset.
+pub fn words(text: &str, prime_is_word_char: bool) -> impl Iterator<Item = (usize, &str)> {
+    text.split(move |c| !is_word_char(c, prime_is_word_char))
+        .filter(|w| !w.is_empty())
+        .map(move |w| (w.as_ptr() as usize - text.as_ptr() as usize, w))
+}
+
+/// Whether `entry` could name a Haskell variable, constructor or operator, possibly
+/// qualified by a module like `Data.Map.lookup`.
+fn is_valid_identifier(entry: &str) -> bool {
+    let mut name = entry;
+    while let Some((module, rest)) = name.split_once('.') {
+        let is_module = module.starts_with(char::is_uppercase)
+            && module
+                .chars()
+                .all(|c| c.is_alphanumeric() || c == '_' || c == '\'');
+        if !is_module || rest.is_empty() {
+            break;
+        }
+        name = rest;
+    }
+
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(c) if c.is_alphabetic() || c == '_' => {
+            chars.all(|c| c.is_alphanumeric() || c == '_' || c == '\'')
+        }
+        Some(_) => name.chars().all(|c| "!#$%&*+./<=>?@\\^|-~:".contains(c)),
+        None => false,
+    }
+}
+
+/// What [`lint_taboo_file`] found in a banned words list.
+#[derive(Debug, Default)]
+pub struct TabooLint {
+    /// Each problem with the list, like `line 3: duplicate entry `foo``.
+    pub problems: Vec<String>,
+    /// The number of blank lines, which are dropped.
+    pub blank_lines: usize,
+    /// How many entries the list has, then how many each section has.
+    pub counts: Vec<String>,
+}
+
+/// Checks the banned words list at `path` for problems, like invalid identifiers,
+/// duplicate entries and queries that don't compile.
+pub fn lint_taboo_file(path: &Path) -> Result<TabooLint, TabooError> {
+    let lines = File::open(path)
+        .and_then(|file| BufReader::new(file).lines().collect::<Result<Vec<_>, _>>())
+        .map_err(|source| TabooError::TabooFile {
+            path: path.to_path_buf(),
+            source,
+        })?;
+
+    // entries are only duplicates of others in the same section
+    let mut first_seen: HashMap<(usize, bool, String), usize> = HashMap::new();
+    let mut section = 0;
+    let mut in_allow = false;
+    let mut lint = TabooLint::default();
+
+    for (index, line) in lines.iter().enumerate() {
+        let line_number = index + 1;
+
+        if in_allow && let Some((_, annotation_problems)) = allowance_line(line) {
+            for problem in annotation_problems {
+                lint.problems.push(format!("line {line_number}: {problem}"));
+            }
+            continue;
+        }
+
+        let taboo_line = taboo_line(line);
+        in_allow = matches!(taboo_line, Some(TabooLine::Allow));
+        let entries = match taboo_line {
+            Some(TabooLine::Entry(entries, annotation_problems)) => {
+                for problem in annotation_problems {
+                    lint.problems.push(format!("line {line_number}: {problem}"));
+                }
+                entries
+            }
+            Some(TabooLine::Section(query, kind_problems)) => {
+                section += 1;
+                for problem in kind_problems {
+                    lint.problems.push(format!("line {line_number}: {problem}"));
+                }
+                if let SectionQuery::Query(query) = &query
+                    && let Err(e) = Query::new(&tree_sitter_haskell::LANGUAGE.into(), query)
+                {
+                    lint.problems
+                        .push(format!("line {line_number}: invalid query `{query}`: {e}"));
+                }
+                continue;
+            }
+            Some(TabooLine::Allow) => continue,
+            None => {
+                lint.blank_lines += 1;
+                continue;
+            }
+        };
+
+        let grouped = entries.len() > 1;
+        for entry in entries {
+            let name = prefix_of(entry.word()).unwrap_or(entry.word());
+            if pattern_of(name).is_none() && !is_valid_identifier(name) {
+                lint.problems.push(format!(
+                    "line {line_number}: `{}` is not a valid Haskell identifier",
+                    entry.word()
+                ));
+            }
+
+            // entries differing only in their annotations still conflict
+            let key = (
+                section,
+                matches!(entry, TabooEntry::Exception(_)),
+                entry.word().to_string(),
+            );
+            if let Some(first) = first_seen.get(&key) {
+                lint.problems.push(format!(
+                    "line {line_number}: duplicate entry `{}` (first seen on line {first})",
+                    if grouped { entry.word() } else { line.trim() }
+                ));
+            } else {
+                first_seen.insert(key, line_number);
+            }
+        }
+    }
+
+    // load the entries again the same way a scan would, if it weren't for duplicates
+    let taboo_set = File::open(path)
+        .and_then(|file| taboo_set_from(file, false))
+        .map_err(|source| TabooError::TabooFile {
+            path: path.to_path_buf(),
+            source,
+        })?;
+    lint.counts.push(format!(
+        "{}: {}",
+        path.display(),
+        taboo_set.describe_counts()
+    ));
+    for section in &taboo_set.sections {
+        lint.counts.push(format!(
+            "  {}: {}",
+            section.query,
+            section.taboo_set.describe_counts()
+        ));
+    }
+    if !taboo_set.allowances.is_empty() {
+        lint.counts.push(format!(
+            "  [allow]: {} allowances",
+            taboo_set.allowances.len()
+        ));
+    }
+
+    Ok(lint)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn list(list: &str) -> TabooSet {
+        banned_words_from(list.as_bytes()).unwrap()
+    }
+
+    fn bans(taboo_set: &TabooSet, text: &str, options: MatchOptions) -> bool {
+        taboo_set
+            .ban_for(text, Path::new("A.hs"), options)
+            .is_some()
+    }
+
+    #[test]
+    fn primes_end_words_unless_prime_is_word_char() {
+        let taboo_set = list("foo\n");
+        assert!(bans(&taboo_set, "foo", MatchOptions::default()));
+        assert!(!bans(&taboo_set, "foo'", MatchOptions::default()));
+
+        let options = MatchOptions {
+            prime_is_word_char: false,
+            ..MatchOptions::default()
+        };
+        assert!(bans(&taboo_set, "foo", options));
+        assert!(bans(&taboo_set, "foo'", options));
+    }
+
+    #[test]
+    fn alias_groups_need_an_identifier_and_a_space() {
+        let taboo_set = list("Config: cfg, conf\n");
+        let matched = taboo_set
+            .ban_for("cfg", Path::new("A.hs"), MatchOptions::default())
+            .unwrap();
+        assert_eq!(matched.canonical, "Config");
+
+        assert_eq!(alias_group("NE.:|"), None);
+        assert_eq!(alias_group("Config:cfg"), None);
+        let taboo_set = list("NE.:|\n");
+        assert!(bans(&taboo_set, "NE.:|", MatchOptions::default()));
+        assert!(!bans(&taboo_set, "|", MatchOptions::default()));
+    }
+
+    #[test]
+    fn fold_leading_case_folds_only_the_first_letter() {
+        let options = MatchOptions {
+            fold_leading_case: true,
+            ..MatchOptions::default()
+        };
+        assert!(bans(&list("foo\n"), "Foo", options));
+        assert!(bans(&list("Foo\n"), "foo", options));
+        assert!(!bans(&list("foo\n"), "Foo", MatchOptions::default()));
+        assert!(!bans(&list("fooBar\n"), "FOOBAR", options));
+
+        let taboo_set = list("foo*\n!fooBar\n");
+        assert_eq!(taboo_set.exception_for("FooBar", options), Some("fooBar"));
+        assert_eq!(
+            taboo_set.exception_for("FooBar", MatchOptions::default()),
+            None
+        );
+    }
+
+    #[test]
+    fn longer_prefixes_apply_where_shorter_ones_are_scoped_out() {
+        let taboo_set = list("int* :: in test/**\ninternal*\n");
+        let ban = |path| {
+            taboo_set
+                .ban_for("internalFoo", Path::new(path), MatchOptions::default())
+                .map(|matched| matched.word)
+        };
+        assert_eq!(ban("src/A.hs"), Some("internal*"));
+        assert_eq!(ban("test/A.hs"), Some("int*"));
+    }
+
+    #[test]
+    fn entries_listed_twice_in_a_section_are_rejected() {
+        let twice = "error :: in src/Core/**\nerror :: in src/Db/**\n";
+        let e = banned_words_from(twice.as_bytes()).unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "line 2: duplicate entry `error` (first seen on line 1)"
+        );
+
+        let taboo_set = list("error :: in src/Core/**, in src/Db/**\n[kinds type]\nerror\n");
+        assert!(!bans(&taboo_set, "error", MatchOptions::default()));
+        assert!(
+            taboo_set
+                .ban_for("error", Path::new("src/Db/A.hs"), MatchOptions::default())
+                .is_some()
+        );
+    }
+}
@@ -0,0 +1,73 @@
+use std::collections::HashMap;
+
+/// Entries that match by prefix, e.g. `internal*`, looked up in time proportional to the
+/// length of the identifier rather than the number of entries.
+#[derive(Debug, Clone)]
+pub struct PrefixTrie<T> {
+    root: TrieNode<T>,
+}
+
+#[derive(Debug, Clone)]
+struct TrieNode<T> {
+    children: HashMap<char, TrieNode<T>>,
+    /// The entry whose prefix ends at this node.
+    entry: Option<T>,
+}
+
+// derived `Default` would needlessly require `T: Default`
+impl<T> Default for PrefixTrie<T> {
+    fn default() -> Self {
+        PrefixTrie {
+            root: TrieNode::default(),
+        }
+    }
+}
+
+impl<T> Default for TrieNode<T> {
+    fn default() -> Self {
+        TrieNode {
+            children: HashMap::new(),
+            entry: None,
+        }
+    }
+}
+
+impl<T> PrefixTrie<T> {
+    pub fn insert(&mut self, prefix: &str, entry: T) {
+        let mut node = &mut self.root;
+        for c in prefix.chars() {
+            node = node.children.entry(c).or_default();
+        }
+        node.entry = Some(entry);
+    }
+
+    /// Returns every entry whose prefix `text` starts with, shortest prefix first.
+    pub fn matching<'s>(&'s self, text: &str) -> impl Iterator<Item = &'s T> {
+        let mut node = Some(&self.root);
+        let mut chars = text.chars();
+        std::iter::from_fn(move || {
+            for c in chars.by_ref() {
+                let next = node?.children.get(&c);
+                node = next;
+                if let Some(entry) = &next?.entry {
+                    return Some(entry);
+                }
+            }
+            None
+        })
+    }
+
+    /// Returns every entry, in no particular order.
+    pub fn values(&self) -> impl Iterator<Item = &T> {
+        let mut pending = vec![&self.root];
+        std::iter::from_fn(move || {
+            while let Some(node) = pending.pop() {
+                pending.extend(node.children.values());
+                if let Some(entry) = &node.entry {
+                    return Some(entry);
+                }
+            }
+            None
+        })
+    }
+}